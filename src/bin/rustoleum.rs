@@ -0,0 +1,269 @@
+// rustoleum: chapter_3 from the command line
+//
+//   rustoleum get /meaningOfLife file.json
+//   rustoleum validate --schema schema.json file.json
+//   rustoleum fmt --indent 4 file.json
+//   rustoleum codegen --name Payload sample1.json sample2.json
+//
+// every command reads stdin when the file is left out or given as "-"
+extern crate rustoleum;
+extern crate serde_json;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use serde_json::Value;
+
+use rustoleum::chapter_3::codegen::rust_types;
+use rustoleum::chapter_3::diagnostic::Diagnostic;
+use rustoleum::chapter_3::{parse_input_to_json_value, Pointer, Printer, Schema};
+use rustoleum::Error;
+
+const USAGE: &str = "usage:
+  rustoleum get [--raw] <pointer> [file]
+  rustoleum validate --schema <schema> [file]
+  rustoleum fmt [--indent N] [--compact-arrays N] [--compact-array-width N] [--ascii] [--minify] [file]
+  rustoleum codegen [--name Root] [file...]
+  rustoleum help";
+
+// exit codes, so a script can tell a broken document from one that just lacks the key
+const FAILURE: i32 = 1;
+const USAGE_ERROR: i32 = 2;
+const PARSE_ERROR: i32 = 3;
+const NOT_FOUND: i32 = 4;
+const INVALID: i32 = 5;
+const IO_ERROR: i32 = 6;
+
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn usage(message: &str) -> Failure {
+        Failure {
+            code: USAGE_ERROR,
+            message: format!("error: {}\n{}", message, USAGE),
+        }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Failure {
+        let code = match error {
+            Error::Parse(_) | Error::Syntax { .. } | Error::LimitExceeded { .. } => PARSE_ERROR,
+            Error::MissingKey { .. } | Error::WrongType { .. } => NOT_FOUND,
+            Error::Validation(_) => INVALID,
+            Error::Io(_) => IO_ERROR,
+            Error::InvalidPointer { .. } | Error::InvalidSchema { .. } => USAGE_ERROR,
+            _ => FAILURE,
+        };
+        Failure {
+            code,
+            message: format!("error: {}", error),
+        }
+    }
+}
+
+type Outcome = Result<String, Failure>;
+
+// options that take a value, flags that don't, and everything else in order
+struct Args {
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String], options: &[&str], flags: &[&str]) -> Result<Args, Failure> {
+        let mut parsed = Args {
+            options: HashMap::new(),
+            flags: Vec::new(),
+            positional: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if options.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Failure::usage(&format!("{} needs a value", arg)))?;
+                parsed.options.insert(arg.clone(), value.clone());
+            } else if flags.contains(&arg.as_str()) {
+                parsed.flags.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(Failure::usage(&format!("unknown option {}", arg)));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn number(&self, name: &str) -> Result<Option<usize>, Failure> {
+        match self.options.get(name) {
+            None => Ok(None),
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| Failure::usage(&format!("{} needs a number, got {:?}", name, value))),
+        }
+    }
+
+    // the single optional input file after `required` positional arguments
+    fn input(&self, required: usize) -> Result<Option<&str>, Failure> {
+        if self.positional.len() < required {
+            return Err(Failure::usage("missing argument"));
+        }
+        match self.positional.len() - required {
+            0 => Ok(None),
+            1 => Ok(Some(&self.positional[required])),
+            _ => Err(Failure::usage("too many arguments")),
+        }
+    }
+}
+
+fn read_input(path: Option<&str>) -> Result<String, Failure> {
+    let mut input = String::new();
+    let read = match path {
+        None | Some("-") => io::stdin().read_to_string(&mut input).map(|_| ()),
+        Some(path) => fs::read_to_string(path).map(|text| input = text),
+    };
+    read.map_err(|e| Failure {
+        code: IO_ERROR,
+        message: format!("error: could not read {}: {}", path.unwrap_or("stdin"), e),
+    })?;
+    Ok(input)
+}
+
+// parse errors get the caret snippet rather than a bare message
+fn parse(input: &str) -> Result<Value, Failure> {
+    parse_input_to_json_value(input).map_err(|e| match Diagnostic::from_error(input, &e) {
+        Some(diagnostic) => Failure {
+            code: PARSE_ERROR,
+            message: diagnostic.render(false).trim_end().to_string(),
+        },
+        None => Failure::from(e),
+    })
+}
+
+fn get(args: &[String]) -> Outcome {
+    let args = Args::parse(args, &[], &["--raw"])?;
+    let input = read_input(args.input(1)?)?;
+    let pointer = Pointer::parse(&args.positional[0])?;
+    let value = parse(&input)?;
+    match *pointer.resolve(&value)? {
+        Value::String(ref s) if args.flag("--raw") => Ok(s.clone()),
+        ref found => Ok(Printer::new().print(found)),
+    }
+}
+
+fn validate(args: &[String]) -> Outcome {
+    let args = Args::parse(args, &["--schema"], &[])?;
+    let schema_path = args
+        .options
+        .get("--schema")
+        .ok_or_else(|| Failure::usage("validate needs --schema <file>"))?;
+    let schema = Schema::new(parse(&read_input(Some(schema_path))?)?)?;
+    let instance = parse(&read_input(args.input(0)?)?)?;
+    match schema.validate(&instance) {
+        Ok(()) => Ok("valid".to_string()),
+        Err(violations) => Err(Failure {
+            code: INVALID,
+            message: violations
+                .iter()
+                .map(|v| format!("invalid: {}", v))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }),
+    }
+}
+
+fn fmt(args: &[String]) -> Outcome {
+    let args = Args::parse(
+        args,
+        &["--indent", "--compact-arrays", "--compact-array-width"],
+        &["--ascii", "--minify"],
+    )?;
+    let value = parse(&read_input(args.input(0)?)?)?;
+    let mut printer = Printer::new().ascii_only(args.flag("--ascii"));
+    if let Some(indent) = args.number("--indent")? {
+        printer = printer.indent(indent);
+    }
+    if let Some(items) = args.number("--compact-arrays")? {
+        printer = printer.compact_arrays(items);
+    }
+    if let Some(width) = args.number("--compact-array-width")? {
+        printer = printer.compact_array_width(width);
+    }
+    Ok(if args.flag("--minify") {
+        printer.minify(&value)
+    } else {
+        printer.print(&value)
+    })
+}
+
+// every file is a sample of the same payload, or an array of them; stdin is the only input when
+// there are no files
+fn codegen(args: &[String]) -> Outcome {
+    let args = Args::parse(args, &["--name"], &[])?;
+    let name = args.options.get("--name").map_or("Root", String::as_str);
+    let inputs = if args.positional.is_empty() {
+        vec![parse(&read_input(None)?)?]
+    } else {
+        args.positional
+            .iter()
+            .map(|path| parse(&read_input(Some(path))?))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let mut samples = Vec::new();
+    for input in inputs {
+        match input {
+            Value::Array(items) => samples.extend(items),
+            other => samples.push(other),
+        }
+    }
+    // a sample that isn't an object is a bad input, not a missing key
+    let source = rust_types(&samples, name).map_err(|error| match error {
+        Error::WrongType { .. } => Failure {
+            code: INVALID,
+            message: format!("error: {}", error),
+        },
+        error => Failure::from(error),
+    })?;
+    Ok(source.trim_end().to_string())
+}
+
+fn run(args: &[String]) -> Outcome {
+    match args.first().map(String::as_str) {
+        Some("get") => get(&args[1..]),
+        Some("validate") => validate(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("codegen") => codegen(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") => Ok(USAGE.to_string()),
+        Some(other) => Err(Failure::usage(&format!("unknown command {:?}", other))),
+        None => Err(Failure::usage("missing command")),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => {
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            // a closed pipe (eg. `| head`) isn't worth a panic
+            let _ = writeln!(stdout, "{}", output);
+        }
+        Err(failure) => {
+            eprintln!("{}", failure.message);
+            process::exit(failure.code);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+#[allow(clippy::let_and_return)] // the let binding is the point of the example
 pub fn meaning_of_life() -> i32 {
     let x = 42;
     x // leave ; off for making this the return expression
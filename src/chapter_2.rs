@@ -1,9 +1,22 @@
+pub mod arith;
+pub mod checked;
+pub mod decimal;
+pub mod ratio;
+pub mod rounding;
+
+pub use self::arith::{Arith, Policy};
+pub use self::checked::{ArithmeticError, CheckedInt};
+pub use self::decimal::Decimal;
+pub use self::ratio::Ratio;
+pub use self::rounding::{divide_with, RoundingMode};
+
 // Option built in
 pub fn divide_safely(a: i32, b: i32) -> Option<i32> {
     if b == 0 { // 1
         None
     } else {
-        Some(a / b)
+        // i32::MIN / -1 doesn't fit either; see checked::CheckedInt for why a division failed
+        a.checked_div(b)
     }
 }
 
@@ -24,6 +37,7 @@ mod tests {
         assert_eq!(divide_safely(4, 2), Some(2));
         assert_eq!(divide_safely(8, 3), Some(2));
         assert_eq!(divide_safely(4, 0), None);
+        assert_eq!(divide_safely(i32::MIN, -1), None);
     }
 
     #[test]
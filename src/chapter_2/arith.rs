@@ -0,0 +1,403 @@
+// an integer that carries its overflow policy in its type, so the same `a + b * c` can be checked
+// in one place and saturating in another without touching the expression
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+use chapter_2::checked::{ArithmeticError, CheckedInt, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+        })
+    }
+}
+
+// CheckedInt plus the two's complement result, for the policies that want it
+pub trait Integer: CheckedInt {
+    fn checked(op: Op, a: Self, b: Self) -> Result<Self>;
+    // only called once the divisor is known not to be zero
+    fn wrapping(op: Op, a: Self, b: Self) -> Self;
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {$(
+        impl Integer for $t {
+            fn checked(op: Op, a: $t, b: $t) -> Result<$t> {
+                match op {
+                    Op::Add => a.try_add(b),
+                    Op::Sub => a.try_sub(b),
+                    Op::Mul => a.try_mul(b),
+                    Op::Div => a.try_div(b),
+                    Op::Rem => a.try_rem(b),
+                }
+            }
+
+            fn wrapping(op: Op, a: $t, b: $t) -> $t {
+                match op {
+                    Op::Add => a.wrapping_add(b),
+                    Op::Sub => a.wrapping_sub(b),
+                    Op::Mul => a.wrapping_mul(b),
+                    Op::Div => a.wrapping_div(b),
+                    Op::Rem => a.wrapping_rem(b),
+                }
+            }
+        }
+    )*};
+}
+
+integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+// what to do when `a op b` doesn't fit; only called once the checked op has failed
+pub trait Policy {
+    fn failed<T: Integer>(op: Op, a: T, b: T, error: ArithmeticError) -> Result<T>;
+}
+
+// keeps the error; every later op on the value keeps it too, like NaN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Checked;
+
+// two's complement, like the `wrapping_*` methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Wrapping;
+
+// clamps to MIN or MAX, whichever way the result went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Saturating;
+
+// panics like a debug build does, but in release too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Panicking;
+
+// keeps the error like Checked, after reporting the operation that failed to L
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trapping<L = Stderr>(PhantomData<L>);
+
+pub trait TrapLog {
+    fn trap(message: &str);
+}
+
+// the default sink, and the one place this library prints anything itself; pick Recorded or
+// your own TrapLog to keep traps off stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stderr;
+
+impl TrapLog for Stderr {
+    fn trap(message: &str) {
+        eprintln!("arithmetic trap: {}", message);
+    }
+}
+
+// keeps the traps on this thread, for tests to look at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Recorded;
+
+thread_local! {
+    static TRAPS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Recorded {
+    // the traps recorded on this thread since the last call
+    pub fn take() -> Vec<String> {
+        TRAPS.with(|traps| traps.borrow_mut().split_off(0))
+    }
+}
+
+impl TrapLog for Recorded {
+    fn trap(message: &str) {
+        TRAPS.with(|traps| traps.borrow_mut().push(message.to_string()));
+    }
+}
+
+// division by zero has no sensible wrapped or saturated answer, so every policy keeps that error
+impl Policy for Checked {
+    fn failed<T: Integer>(_: Op, _: T, _: T, error: ArithmeticError) -> Result<T> {
+        Err(error)
+    }
+}
+
+impl Policy for Wrapping {
+    fn failed<T: Integer>(op: Op, a: T, b: T, error: ArithmeticError) -> Result<T> {
+        match error {
+            ArithmeticError::DivideByZero => Err(error),
+            _ => Ok(T::wrapping(op, a, b)),
+        }
+    }
+}
+
+impl Policy for Saturating {
+    fn failed<T: Integer>(_: Op, _: T, _: T, error: ArithmeticError) -> Result<T> {
+        match error {
+            ArithmeticError::DivideByZero => Err(error),
+            ArithmeticError::Overflow => Ok(T::MAX),
+            ArithmeticError::Underflow => Ok(T::MIN),
+        }
+    }
+}
+
+impl Policy for Panicking {
+    fn failed<T: Integer>(op: Op, a: T, b: T, error: ArithmeticError) -> Result<T> {
+        panic!("{} {} {}: {}", a, op, b, error)
+    }
+}
+
+impl<L: TrapLog> Policy for Trapping<L> {
+    fn failed<T: Integer>(op: Op, a: T, b: T, error: ArithmeticError) -> Result<T> {
+        L::trap(&format!("{} {} {}: {}", a, op, b, error));
+        Err(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arith<T, P> {
+    value: Result<T>,
+    policy: PhantomData<P>,
+}
+
+impl<T: Integer, P: Policy> Arith<T, P> {
+    pub fn new(value: T) -> Arith<T, P> {
+        Arith {
+            value: Ok(value),
+            policy: PhantomData,
+        }
+    }
+
+    // the result, or the first error if any op along the way failed
+    pub fn value(self) -> Result<T> {
+        self.value
+    }
+
+    pub fn apply(self, op: Op, rhs: Arith<T, P>) -> Arith<T, P> {
+        let value = match (self.value, rhs.value) {
+            (Ok(a), Ok(b)) => T::checked(op, a, b).or_else(|error| P::failed(op, a, b, error)),
+            (Err(error), _) | (_, Err(error)) => Err(error),
+        };
+        Arith {
+            value,
+            policy: PhantomData,
+        }
+    }
+
+    // the same value under another policy, eg. to finish a checked calculation saturating
+    pub fn with_policy<Q: Policy>(self) -> Arith<T, Q> {
+        Arith {
+            value: self.value,
+            policy: PhantomData,
+        }
+    }
+}
+
+impl<T: Integer, P: Policy> From<T> for Arith<T, P> {
+    fn from(value: T) -> Arith<T, P> {
+        Arith::new(value)
+    }
+}
+
+impl<T: fmt::Display, P> fmt::Display for Arith<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Ok(ref value) => value.fmt(f),
+            Err(ref error) => error.fmt(f),
+        }
+    }
+}
+
+// `Arith op Arith` and `Arith op T`, so call sites only wrap the first operand
+macro_rules! operator {
+    ($($trait:ident $method:ident $op:ident),*) => {$(
+        impl<T: Integer, P: Policy> $trait for Arith<T, P> {
+            type Output = Arith<T, P>;
+
+            fn $method(self, rhs: Arith<T, P>) -> Arith<T, P> {
+                self.apply(Op::$op, rhs)
+            }
+        }
+
+        impl<T: Integer, P: Policy> $trait<T> for Arith<T, P> {
+            type Output = Arith<T, P>;
+
+            fn $method(self, rhs: T) -> Arith<T, P> {
+                self.apply(Op::$op, Arith::new(rhs))
+            }
+        }
+    )*};
+}
+
+operator!(Add add Add, Sub sub Sub, Mul mul Mul, Div div Div, Rem rem Rem);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPS: [Op; 5] = [Op::Add, Op::Sub, Op::Mul, Op::Div, Op::Rem];
+
+    // the exact answer in i128, and whether it went past even that; only u64 * u64 can, and
+    // then the low bits are still the wrapped answer
+    fn exact(op: Op, a: i128, b: i128) -> Option<(i128, bool)> {
+        match op {
+            Op::Add => Some((a + b, false)),
+            Op::Sub => Some((a - b, false)),
+            Op::Mul => Some(a.overflowing_mul(b)),
+            Op::Div if b == 0 => None,
+            Op::Div => Some((a / b, false)),
+            Op::Rem if b == 0 => None,
+            Op::Rem => Some((a % b, false)),
+        }
+    }
+
+    // random operands, with the edges of the type turning up often enough to matter
+    macro_rules! property {
+        ($name:ident, $t:ty, $seed:expr) => {
+            #[test]
+            fn $name() {
+                // xorshift, so the cases are the same on every run
+                let mut state: u64 = $seed;
+                let mut next = || {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state
+                };
+                let edges: [$t; 7] = [<$t>::MIN, <$t>::MAX, 0, 1, 2, <$t>::MIN / 2, <$t>::MAX / 2];
+                let mut operand = |r: u64| -> $t {
+                    match r % 4 {
+                        0 => edges[(r >> 8) as usize % edges.len()],
+                        1 => (r >> 8) as $t % 100,
+                        _ => next() as $t,
+                    }
+                };
+                for case in 0..10_000 {
+                    let (a, b) = (operand(case * 7919 + 1), operand(case * 104_729 + 3));
+                    let (wa, wb) = (a as i128, b as i128);
+                    for &op in &OPS {
+                        let checked = Arith::<$t, Checked>::new(a).apply(op, b.into()).value();
+                        let wrapping = Arith::<$t, Wrapping>::new(a).apply(op, b.into()).value();
+                        let saturating =
+                            Arith::<$t, Saturating>::new(a).apply(op, b.into()).value();
+                        let trapping = Arith::<$t, Trapping<Recorded>>::new(a)
+                            .apply(op, b.into())
+                            .value();
+                        let traps = Recorded::take();
+                        let context = format!("{} {} {}", a, op, b);
+                        match exact(op, wa, wb) {
+                            None => {
+                                for result in &[checked, wrapping, saturating, trapping] {
+                                    assert_eq!(*result, Err(ArithmeticError::DivideByZero));
+                                }
+                                assert_eq!(traps.len(), 1);
+                            }
+                            Some((n, huge))
+                                if huge || n < <$t>::MIN as i128 || n > <$t>::MAX as i128 =>
+                            {
+                                let error = if huge || n > 0 {
+                                    ArithmeticError::Overflow
+                                } else {
+                                    ArithmeticError::Underflow
+                                };
+                                assert_eq!(checked, Err(error), "{}", context);
+                                assert_eq!(wrapping, Ok(n as $t), "{}", context);
+                                let clamped = if huge || n > 0 { <$t>::MAX } else { <$t>::MIN };
+                                assert_eq!(saturating, Ok(clamped), "{}", context);
+                                assert_eq!(trapping, Err(error), "{}", context);
+                                assert_eq!(traps, vec![format!("{}: {}", context, error)]);
+                            }
+                            Some((n, _)) => {
+                                for result in &[checked, wrapping, saturating, trapping] {
+                                    assert_eq!(*result, Ok(n as $t), "{}", context);
+                                }
+                                // Panicking only gets the cases it doesn't panic on here; catching
+                                // thousands of panics would bury the test output in messages
+                                let panicking =
+                                    Arith::<$t, Panicking>::new(a).apply(op, b.into()).value();
+                                assert_eq!(panicking, Ok(n as $t), "{}", context);
+                                assert!(traps.is_empty());
+                            }
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    property!(matches_i128_for_i32, i32, 0x2545_f491_4f6c_dd1d);
+    property!(matches_i128_for_i64, i64, 0x9e37_79b9_7f4a_7c15);
+    property!(matches_i128_for_u32, u32, 0xdead_beef_cafe_f00d);
+    property!(matches_i128_for_u64, u64, 0x0123_4567_89ab_cdef);
+
+    #[test]
+    fn operators_follow_the_policy() {
+        let checked = Arith::<u8, Checked>::new(200) + 100 - 50;
+        assert_eq!(checked.value(), Err(ArithmeticError::Overflow));
+        assert_eq!(checked.to_string(), "arithmetic overflow");
+        assert_eq!(
+            (Arith::<u8, Wrapping>::new(200) + 100 - 50).value(),
+            Ok(250)
+        );
+        assert_eq!(
+            (Arith::<u8, Saturating>::new(200) + 100 - 50).value(),
+            Ok(205)
+        );
+        let sum = Arith::<i32, Checked>::new(2) * Arith::new(3) + 4;
+        assert_eq!(sum.to_string(), "10");
+        assert_eq!(
+            (Arith::<i32, Saturating>::new(i32::MIN) / -1).value(),
+            Ok(i32::MAX)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "2147483647 + 1: arithmetic overflow")]
+    fn panicking_panics() {
+        let _ = Arith::<i32, Panicking>::new(i32::MAX) + 1;
+    }
+
+    #[test]
+    #[should_panic(expected = "0 - 1: arithmetic underflow")]
+    fn panicking_panics_on_underflow() {
+        let _ = Arith::<u64, Panicking>::new(0) - 1;
+    }
+
+    #[test]
+    #[should_panic(expected = "7 % 0: division by zero")]
+    fn panicking_panics_on_division_by_zero() {
+        let _ = Arith::<i64, Panicking>::new(7) % 0;
+    }
+
+    #[test]
+    fn errors_stick_and_switch_policy() {
+        // the same expression, written once, under two policies
+        fn total<P: Policy>(prices: &[u32]) -> Result<u32> {
+            prices
+                .iter()
+                .fold(Arith::<u32, P>::new(0), |sum, &price| {
+                    sum + Arith::new(price) * 2
+                })
+                .value()
+        }
+        let prices = [u32::MAX / 2, 1, 7];
+        assert_eq!(total::<Checked>(&prices), Err(ArithmeticError::Overflow));
+        assert_eq!(total::<Saturating>(&prices), Ok(u32::MAX));
+        assert_eq!(total::<Wrapping>(&prices), Ok(14));
+
+        let poisoned = Arith::<i8, Trapping<Recorded>>::new(1) / 0 + 1;
+        assert_eq!(poisoned.value(), Err(ArithmeticError::DivideByZero));
+        assert_eq!(Recorded::take(), vec!["1 / 0: division by zero"]);
+        let saturated = (Arith::<i8, Checked>::new(100) * 2).with_policy::<Saturating>() + 1;
+        assert_eq!(saturated.value(), Err(ArithmeticError::Overflow));
+    }
+}
@@ -0,0 +1,267 @@
+// checked arithmetic for every primitive integer, saying why an operation failed instead of
+// handing back a bare None
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArithmeticError {
+    DivideByZero,
+    // the exact result is above the type's MAX
+    Overflow,
+    // the exact result is below the type's MIN, eg. 0u8 - 1 or i8::MIN - 1
+    Underflow,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ArithmeticError::DivideByZero => "division by zero",
+            ArithmeticError::Overflow => "arithmetic overflow",
+            ArithmeticError::Underflow => "arithmetic underflow",
+        })
+    }
+}
+
+impl error::Error for ArithmeticError {}
+
+pub type Result<T> = ::std::result::Result<T, ArithmeticError>;
+
+pub trait CheckedInt: Copy + Ord + fmt::Debug + fmt::Display {
+    const ZERO: Self;
+    const ONE: Self;
+    const MIN: Self;
+    const MAX: Self;
+    const BITS: u32;
+    const SIGNED: bool;
+
+    fn try_add(self, rhs: Self) -> Result<Self>;
+    fn try_sub(self, rhs: Self) -> Result<Self>;
+    fn try_mul(self, rhs: Self) -> Result<Self>;
+    // truncates toward zero like `/`; MIN / -1 is the one overflow
+    fn try_div(self, rhs: Self) -> Result<Self>;
+    // MIN % -1 is 0, which fits, so it's Ok even though `%` would panic
+    fn try_rem(self, rhs: Self) -> Result<Self>;
+    fn try_pow(self, exp: u32) -> Result<Self>;
+    fn try_neg(self) -> Result<Self>;
+    // a multiply by 2^n, so shifting bits out is an overflow; so is shifting by BITS or more
+    fn try_shl(self, n: u32) -> Result<Self>;
+    // rounds toward negative infinity, like `>>`; only a shift by BITS or more fails
+    fn try_shr(self, n: u32) -> Result<Self>;
+    // the nearest f64, which for 64 bits and up may not be exact
+    fn to_f64(self) -> f64;
+    // only a whole number that's in range
+    fn from_f64(x: f64) -> Option<Self>;
+
+    fn is_negative(self) -> bool {
+        self < Self::ZERO
+    }
+}
+
+// which way an out-of-range result went, given whether the exact answer is negative
+fn out_of_range(negative: bool) -> ArithmeticError {
+    if negative {
+        ArithmeticError::Underflow
+    } else {
+        ArithmeticError::Overflow
+    }
+}
+
+macro_rules! checked_int {
+    ($($t:ident: $signed:expr),*) => {$(
+        impl CheckedInt for $t {
+            const ZERO: $t = 0;
+            const ONE: $t = 1;
+            const MIN: $t = $t::MIN;
+            const MAX: $t = $t::MAX;
+            const BITS: u32 = $t::BITS;
+            const SIGNED: bool = $signed;
+
+            fn try_add(self, rhs: $t) -> Result<$t> {
+                self.checked_add(rhs).ok_or_else(|| out_of_range(rhs.is_negative()))
+            }
+
+            fn try_sub(self, rhs: $t) -> Result<$t> {
+                self.checked_sub(rhs).ok_or_else(|| out_of_range(!rhs.is_negative()))
+            }
+
+            fn try_mul(self, rhs: $t) -> Result<$t> {
+                self.checked_mul(rhs)
+                    .ok_or_else(|| out_of_range(self.is_negative() != rhs.is_negative()))
+            }
+
+            fn try_div(self, rhs: $t) -> Result<$t> {
+                if rhs == 0 {
+                    return Err(ArithmeticError::DivideByZero);
+                }
+                self.checked_div(rhs).ok_or(ArithmeticError::Overflow)
+            }
+
+            fn try_rem(self, rhs: $t) -> Result<$t> {
+                if rhs == 0 {
+                    return Err(ArithmeticError::DivideByZero);
+                }
+                Ok(self.checked_rem(rhs).unwrap_or(0))
+            }
+
+            fn try_pow(self, exp: u32) -> Result<$t> {
+                self.checked_pow(exp)
+                    .ok_or_else(|| out_of_range(self.is_negative() && exp % 2 == 1))
+            }
+
+            fn try_neg(self) -> Result<$t> {
+                self.checked_neg().ok_or_else(|| out_of_range(!self.is_negative()))
+            }
+
+            fn try_shl(self, n: u32) -> Result<$t> {
+                let shifted = self.checked_shl(n).ok_or(ArithmeticError::Overflow)?;
+                if shifted >> n != self {
+                    return Err(out_of_range(self.is_negative()));
+                }
+                Ok(shifted)
+            }
+
+            fn try_shr(self, n: u32) -> Result<$t> {
+                self.checked_shr(n).ok_or(ArithmeticError::Overflow)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(x: f64) -> Option<$t> {
+                // 2^(BITS - 1) or 2^BITS, which f64 holds exactly where MAX it can't
+                let end = 2f64.powi($t::BITS as i32 - $signed as i32);
+                let start = if $signed { -end } else { 0.0 };
+                if x.fract() == 0.0 && x >= start && x < end {
+                    Some(x as $t)
+                } else {
+                    None
+                }
+            }
+        }
+    )*};
+}
+
+checked_int!(
+    i8: true, i16: true, i32: true, i64: true, i128: true, isize: true,
+    u8: false, u16: false, u32: false, u64: false, u128: false, usize: false
+);
+
+// generic forms of the operators, for code that's written over any CheckedInt
+pub fn add<T: CheckedInt>(a: T, b: T) -> Result<T> {
+    a.try_add(b)
+}
+
+pub fn sub<T: CheckedInt>(a: T, b: T) -> Result<T> {
+    a.try_sub(b)
+}
+
+pub fn mul<T: CheckedInt>(a: T, b: T) -> Result<T> {
+    a.try_mul(b)
+}
+
+pub fn div<T: CheckedInt>(a: T, b: T) -> Result<T> {
+    a.try_div(b)
+}
+
+pub fn rem<T: CheckedInt>(a: T, b: T) -> Result<T> {
+    a.try_rem(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArithmeticError::*;
+    use super::*;
+
+    // what a checked op on T should return for an exact result computed in i128
+    macro_rules! expect {
+        ($t:ty, $exact:expr) => {{
+            let exact: i128 = $exact;
+            if exact > <$t>::MAX as i128 {
+                Err(Overflow)
+            } else if exact < <$t>::MIN as i128 {
+                Err(Underflow)
+            } else {
+                Ok(exact as $t)
+            }
+        }};
+    }
+
+    // every pair of operands for the 8-bit types, against the same sums done in i128
+    macro_rules! exhaustive {
+        ($($name:ident: $t:ty),*) => {$(
+            #[test]
+            fn $name() {
+                for a in <$t>::MIN..=<$t>::MAX {
+                    let wide = a as i128;
+                    assert_eq!(a.try_neg(), expect!($t, -wide), "-{}", a);
+                    for exp in 0..10 {
+                        assert_eq!(a.try_pow(exp), expect!($t, wide.pow(exp)), "{}^{}", a, exp);
+                    }
+                    for n in 0..<$t>::BITS {
+                        assert_eq!(a.try_shl(n), expect!($t, wide << n), "{} << {}", a, n);
+                        assert_eq!(a.try_shr(n), Ok((wide >> n) as $t), "{} >> {}", a, n);
+                    }
+                    for b in <$t>::MIN..=<$t>::MAX {
+                        let other = b as i128;
+                        assert_eq!(add(a, b), expect!($t, wide + other), "{} + {}", a, b);
+                        assert_eq!(sub(a, b), expect!($t, wide - other), "{} - {}", a, b);
+                        assert_eq!(mul(a, b), expect!($t, wide * other), "{} * {}", a, b);
+                        if b == 0 {
+                            assert_eq!(div(a, b), Err(DivideByZero));
+                            assert_eq!(rem(a, b), Err(DivideByZero));
+                        } else {
+                            assert_eq!(div(a, b), expect!($t, wide / other), "{} / {}", a, b);
+                            assert_eq!(rem(a, b), expect!($t, wide % other), "{} % {}", a, b);
+                        }
+                    }
+                }
+            }
+        )*};
+    }
+
+    exhaustive!(every_i8: i8, every_u8: u8);
+
+    #[test]
+    fn classifies_the_wide_types() {
+        assert_eq!(i32::MIN.try_div(-1), Err(Overflow));
+        assert_eq!(i32::MIN.try_rem(-1), Ok(0));
+        assert_eq!(i128::MAX.try_add(1), Err(Overflow));
+        assert_eq!(i128::MIN.try_sub(1), Err(Underflow));
+        assert_eq!(i64::MIN.try_mul(-1), Err(Overflow));
+        assert_eq!(i64::MAX.try_mul(-2), Err(Underflow));
+        assert_eq!(0u64.try_sub(1), Err(Underflow));
+        assert_eq!(1u128.try_neg(), Err(Underflow));
+        assert_eq!(0usize.try_neg(), Ok(0));
+        assert_eq!(isize::MIN.try_neg(), Err(Overflow));
+        assert_eq!((-2i16).try_pow(15), Ok(i16::MIN));
+        assert_eq!((-2i16).try_pow(17), Err(Underflow));
+        assert_eq!(u32::MAX.try_div(0), Err(DivideByZero));
+        assert_eq!(1u32.try_shl(31), Ok(1 << 31));
+        assert_eq!(1i32.try_shl(31), Err(Overflow));
+        assert_eq!(0i32.try_shl(32), Err(Overflow));
+        assert_eq!((-1i8).try_shl(7), Ok(i8::MIN));
+        assert_eq!((-1i64).try_shr(64), Err(Overflow));
+        assert_eq!((-1i64).try_shr(63), Ok(-1));
+        assert_eq!(Overflow.to_string(), "arithmetic overflow");
+        assert_eq!(i64::from_f64(-2f64.powi(63)), Some(i64::MIN));
+        assert_eq!(i64::from_f64(2f64.powi(63)), None);
+        assert_eq!(u8::from_f64(255.0), Some(255));
+        assert_eq!(u8::from_f64(-1.0), None);
+        assert_eq!(i32::from_f64(1.5), None);
+        assert_eq!(i32::from_f64(f64::NAN), None);
+        assert_eq!(u128::MAX.to_f64(), 3.402823669209385e38);
+    }
+
+    #[test]
+    fn generic_over_the_trait() {
+        fn sum<T: CheckedInt>(values: &[T]) -> Result<T> {
+            values
+                .iter()
+                .try_fold(T::ZERO, |total, &v| total.try_add(v))
+        }
+        assert_eq!(sum(&[100u8, 100, 55]), Ok(255));
+        assert_eq!(sum(&[100u8, 100, 56]), Err(Overflow));
+        assert_eq!(sum(&[-100i8, -28, -1]), Err(Underflow));
+    }
+}
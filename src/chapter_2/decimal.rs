@@ -0,0 +1,606 @@
+// fixed-point decimals for money: an i128 count of 10^-scale units, so 0.1 + 0.2 is exactly 0.3
+// and every rounding is one somebody asked for
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::str::FromStr;
+
+use chapter_2::checked::{ArithmeticError, CheckedInt, Result};
+use chapter_2::ratio::Ratio;
+use chapter_2::rounding::{divide_rounded, divide_with, RoundingMode};
+use chapter_3::exact::ExactNumber;
+
+// the most places an i128 can scale by, as 10^38 is the biggest power of ten it holds
+pub const MAX_SCALE: u32 = 38;
+
+// equal and ordered by value, so 1.5 == 1.50 even though they print differently
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDecimalError {
+    pub input: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid decimal {:?}: {}", self.input, self.reason)
+    }
+}
+
+impl error::Error for ParseDecimalError {}
+
+fn pow10(places: u32) -> Result<i128> {
+    10i128.try_pow(places)
+}
+
+impl Decimal {
+    // mantissa * 10^-scale, so new(1999, 2) is 19.99
+    pub fn new(mantissa: i128, scale: u32) -> Result<Decimal> {
+        if scale > MAX_SCALE {
+            return Err(ArithmeticError::Overflow);
+        }
+        Ok(Decimal { mantissa, scale })
+    }
+
+    pub fn from_integer(n: i128) -> Decimal {
+        Decimal {
+            mantissa: n,
+            scale: 0,
+        }
+    }
+
+    pub fn zero() -> Decimal {
+        Decimal::from_integer(0)
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa < 0
+    }
+
+    // more places is exact; fewer rounds by `mode`
+    pub fn rescale(self, scale: u32, mode: RoundingMode) -> Result<Decimal> {
+        if scale >= self.scale {
+            let mantissa = self.mantissa.try_mul(pow10(scale - self.scale)?)?;
+            return Decimal::new(mantissa, scale);
+        }
+        let mantissa = divide_rounded(self.mantissa, pow10(self.scale - scale)?, mode)?;
+        Decimal::new(mantissa, scale)
+    }
+
+    // at most `places` places, eg. to the cent
+    pub fn round(self, places: u32, mode: RoundingMode) -> Result<Decimal> {
+        if places >= self.scale {
+            Ok(self)
+        } else {
+            self.rescale(places, mode)
+        }
+    }
+
+    // sums and differences are exact, at the larger of the two scales
+    pub fn try_add(self, other: Decimal) -> Result<Decimal> {
+        let (a, b, scale) = align(self, other)?;
+        Decimal::new(a.try_add(b)?, scale)
+    }
+
+    pub fn try_sub(self, other: Decimal) -> Result<Decimal> {
+        let (a, b, scale) = align(self, other)?;
+        Decimal::new(a.try_sub(b)?, scale)
+    }
+
+    // exact too, with the scales added: 1.5 * 2.25 is 3.375
+    pub fn try_mul(self, other: Decimal) -> Result<Decimal> {
+        Decimal::new(
+            self.mantissa.try_mul(other.mantissa)?,
+            self.scale + other.scale,
+        )
+    }
+
+    // a quotient is rarely exact, so it's always to `scale` places rounded by `mode`
+    pub fn try_div(self, other: Decimal, scale: u32, mode: RoundingMode) -> Result<Decimal> {
+        if other.mantissa == 0 {
+            return Err(ArithmeticError::DivideByZero);
+        }
+        // (a / 10^sa) / (b / 10^sb) * 10^scale == a * 10^(sb + scale - sa) / b
+        let shift = i64::from(other.scale) + i64::from(scale) - i64::from(self.scale);
+        let (numer, denom) = if shift >= 0 {
+            (self.mantissa.try_mul(pow10(shift as u32)?)?, other.mantissa)
+        } else {
+            (
+                self.mantissa,
+                other.mantissa.try_mul(pow10((-shift) as u32)?)?,
+            )
+        };
+        Decimal::new(divide_rounded(numer, denom, mode)?, scale)
+    }
+
+    pub fn try_neg(self) -> Result<Decimal> {
+        Decimal::new(self.mantissa.try_neg()?, self.scale)
+    }
+
+    pub fn try_abs(self) -> Result<Decimal> {
+        if self.is_negative() {
+            self.try_neg()
+        } else {
+            Ok(self)
+        }
+    }
+
+    // exact, as 10^scale always fits
+    pub fn to_ratio(self) -> Ratio<i128> {
+        Ratio::new(
+            self.mantissa,
+            pow10(self.scale).expect("scale is at most MAX_SCALE"),
+        )
+        .expect("10^scale isn't zero and divides by its gcd")
+    }
+
+    pub fn from_ratio(ratio: Ratio<i128>, scale: u32, mode: RoundingMode) -> Result<Decimal> {
+        Decimal::from_integer(ratio.numer()).try_div(
+            Decimal::from_integer(ratio.denom()),
+            scale,
+            mode,
+        )
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.to_ratio().to_f64()
+    }
+
+    // splits into `parts` amounts at this scale that add back up to exactly this amount
+    pub fn allocate(self, parts: usize) -> Result<Vec<Decimal>> {
+        self.allocate_by(&vec![1; parts])
+    }
+
+    // largest remainder: everyone gets their share rounded down, then the units left over go
+    // one each to the biggest remainders, earlier parts first on a tie; a negative amount is
+    // split like its magnitude so -100 by three is the mirror of 100 by three
+    pub fn allocate_by(self, weights: &[u64]) -> Result<Vec<Decimal>> {
+        let total = weights
+            .iter()
+            .try_fold(0i128, |sum, &weight| sum.try_add(i128::from(weight)))?;
+        if total == 0 {
+            return Err(ArithmeticError::DivideByZero);
+        }
+        let units = self
+            .mantissa
+            .checked_abs()
+            .ok_or(ArithmeticError::Overflow)?;
+        let mut shares = Vec::with_capacity(weights.len());
+        let mut remainders = Vec::with_capacity(weights.len());
+        let mut left = units;
+        for (i, &weight) in weights.iter().enumerate() {
+            let (share, remainder) = divide_with(
+                units.try_mul(i128::from(weight))?,
+                total,
+                RoundingMode::TowardZero,
+            )?;
+            shares.push(share);
+            remainders.push((remainder, i));
+            left -= share;
+        }
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for &(_, i) in remainders.iter().take(left as usize) {
+            shares[i] += 1;
+        }
+        shares
+            .into_iter()
+            .map(|share| {
+                let mantissa = if self.is_negative() { -share } else { share };
+                Decimal::new(mantissa, self.scale)
+            })
+            .collect()
+    }
+}
+
+// both mantissas at the larger scale
+fn align(a: Decimal, b: Decimal) -> Result<(i128, i128, u32)> {
+    let scale = a.scale.max(b.scale);
+    let a = a.rescale(scale, RoundingMode::TowardZero)?;
+    let b = b.rescale(scale, RoundingMode::TowardZero)?;
+    Ok((a.mantissa, b.mantissa, scale))
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        self.to_ratio().cmp(&other.to_ratio())
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_ratio().hash(state);
+    }
+}
+
+// every place the scale asks for, trailing zeros and all: 19.90, not 19.9
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let places = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = places + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places);
+        if self.is_negative() {
+            f.write_str("-")?;
+        }
+        if places == 0 {
+            f.write_str(whole)
+        } else {
+            write!(f, "{}.{}", whole, fraction)
+        }
+    }
+}
+
+// "19.99", "-0.50" or "+3"; the scale is the number of places written
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> ::std::result::Result<Decimal, ParseDecimalError> {
+        let error = |reason| ParseDecimalError {
+            input: s.to_string(),
+            reason,
+        };
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (whole, fraction) = match unsigned.split_once('.') {
+            Some((whole, fraction)) if !fraction.is_empty() => (whole, fraction),
+            Some(_) => return Err(error("has nothing after the decimal point")),
+            None => (unsigned, ""),
+        };
+        if whole.is_empty()
+            || !whole
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return Err(error("is not a decimal number"));
+        }
+        if fraction.len() > MAX_SCALE as usize {
+            return Err(error("has too many decimal places"));
+        }
+        Ok(Decimal {
+            mantissa: accumulate(whole.bytes().chain(fraction.bytes()), negative)
+                .map_err(|_| error("is out of range"))?,
+            scale: fraction.len() as u32,
+        })
+    }
+}
+
+// a JSON number read exactly, keeping the places as written so 19.90 stays 19.90
+impl TryFrom<&ExactNumber> for Decimal {
+    type Error = ParseDecimalError;
+
+    fn try_from(number: &ExactNumber) -> ::std::result::Result<Decimal, ParseDecimalError> {
+        let error = |reason| ParseDecimalError {
+            input: number.as_str().to_string(),
+            reason,
+        };
+        let scale = number.places().max(0);
+        if scale > i64::from(MAX_SCALE) {
+            return Err(error("has too many decimal places"));
+        }
+        // value * 10^scale is a whole number: the digits followed by this many zeros
+        let zeros = i128::from(number.exponent()) + i128::from(scale);
+        if !number.is_zero() && number.digits().len() as i128 + zeros > 39 {
+            return Err(error("is out of range"));
+        }
+        let digits = number
+            .digits()
+            .bytes()
+            .chain(iter::repeat_n(b'0', zeros as usize));
+        Ok(Decimal {
+            mantissa: accumulate(digits, number.is_negative())
+                .map_err(|_| error("is out of range"))?,
+            scale: scale as u32,
+        })
+    }
+}
+
+// ASCII digits into a mantissa, built toward the final sign so the most negative one fits too
+fn accumulate<I: Iterator<Item = u8>>(mut digits: I, negative: bool) -> Result<i128> {
+    digits.try_fold(0i128, |mantissa, digit| {
+        let digit = i128::from(digit - b'0');
+        let shifted = mantissa.try_mul(10)?;
+        if negative {
+            shifted.try_sub(digit)
+        } else {
+            shifted.try_add(digit)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints() {
+        for text in &["0", "19.99", "-0.05", "0.10", "123456789.000000001", "-7"] {
+            assert_eq!(d(text).to_string(), *text);
+        }
+        assert_eq!(d("+3.50").to_string(), "3.50");
+        assert_eq!(d("0.10").scale(), 2);
+        assert_eq!(d("-0.05").mantissa(), -5);
+        assert_eq!(
+            Decimal::new(i128::MIN, 2).unwrap().to_string().parse(),
+            Ok(Decimal::new(i128::MIN, 2).unwrap())
+        );
+        for bad in &["", "-", ".5", "1.", "1e3", "1,000", "1.2.3", "--1", " 1"] {
+            assert!(bad.parse::<Decimal>().is_err(), "{:?}", bad);
+        }
+        assert_eq!(
+            "1.".parse::<Decimal>().unwrap_err().to_string(),
+            "invalid decimal \"1.\": has nothing after the decimal point"
+        );
+        let too_big = format!("{}0", i128::MAX);
+        assert_eq!(
+            too_big.parse::<Decimal>().unwrap_err().reason,
+            "is out of range"
+        );
+        assert_eq!(Decimal::new(1, 39), Err(ArithmeticError::Overflow));
+    }
+
+    #[test]
+    fn converts_exact_json_numbers_losslessly() {
+        let exact = |text: &str| Decimal::try_from(&text.parse::<ExactNumber>().unwrap());
+        for &(text, expected) in &[
+            ("19.90", "19.90"),
+            ("1990e-2", "19.90"),
+            ("-1.5e3", "-1500"),
+            ("0.000", "0.000"),
+            ("-0", "0"),
+            ("1e-38", "0.00000000000000000000000000000000000001"),
+            (
+                "-170141183460469231731687303715884105728",
+                "-170141183460469231731687303715884105728",
+            ),
+        ] {
+            assert_eq!(exact(text).unwrap().to_string(), expected, "{}", text);
+        }
+        assert_eq!(exact("0.30").unwrap(), d("0.1").try_add(d("0.2")).unwrap());
+        for &(text, reason) in &[
+            ("1e-39", "has too many decimal places"),
+            ("1e39", "is out of range"),
+            ("1e400", "is out of range"),
+            (
+                "17014118346046923173168730371588410572.8",
+                "is out of range",
+            ),
+        ] {
+            assert_eq!(exact(text).unwrap_err().reason, reason, "{}", text);
+        }
+    }
+
+    #[test]
+    fn adds_exactly() {
+        assert_eq!(d("0.1").try_add(d("0.2")).unwrap().to_string(), "0.3");
+        assert_eq!(
+            d("19.99").try_add(d("0.010")).unwrap().to_string(),
+            "20.000"
+        );
+        assert_eq!(d("5").try_sub(d("7.25")).unwrap().to_string(), "-2.25");
+        assert_eq!(d("1.5").try_mul(d("2.25")).unwrap().to_string(), "3.375");
+        assert_eq!(
+            Decimal::from_integer(i128::MAX).try_add(d("1")),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            Decimal::from_integer(i128::MAX).try_add(d("0.1")),
+            Err(ArithmeticError::Overflow)
+        );
+    }
+
+    #[test]
+    fn compares_by_value() {
+        assert_eq!(d("1.5"), d("1.50"));
+        assert!(d("-0.01") < d("0"));
+        assert!(d("2.5") > d("2.499999"));
+        let mut amounts = vec![d("3"), d("-1.5"), d("2.75"), d("0.0")];
+        amounts.sort();
+        assert_eq!(amounts, vec![d("-1.5"), d("0"), d("2.75"), d("3")]);
+        let set: ::std::collections::HashSet<_> =
+            [d("1.5"), d("1.50"), d("1.500")].iter().cloned().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn divides_with_explicit_rounding() {
+        let ten = d("10");
+        assert_eq!(
+            ten.try_div(d("3"), 2, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "3.33"
+        );
+        assert_eq!(
+            ten.try_div(d("3"), 2, RoundingMode::Ceiling)
+                .unwrap()
+                .to_string(),
+            "3.34"
+        );
+        assert_eq!(
+            ten.try_div(d("-3"), 2, RoundingMode::Floor)
+                .unwrap()
+                .to_string(),
+            "-3.34"
+        );
+        assert_eq!(
+            d("1.00")
+                .try_div(d("0.08"), 0, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "12"
+        );
+        assert_eq!(
+            d("1.00")
+                .try_div(d("0.08"), 0, RoundingMode::HalfAwayFromZero)
+                .unwrap()
+                .to_string(),
+            "13"
+        );
+        assert_eq!(
+            d("0.5")
+                .try_div(d("0.25"), 3, RoundingMode::TowardZero)
+                .unwrap()
+                .to_string(),
+            "2.000"
+        );
+        assert_eq!(
+            d("1").try_div(d("0.00"), 2, RoundingMode::HalfEven),
+            Err(ArithmeticError::DivideByZero)
+        );
+        assert_eq!(
+            d("2.345")
+                .round(2, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "2.34"
+        );
+        assert_eq!(
+            d("2.345")
+                .round(2, RoundingMode::HalfAwayFromZero)
+                .unwrap()
+                .to_string(),
+            "2.35"
+        );
+        assert_eq!(
+            d("-2.5")
+                .round(0, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "-2"
+        );
+        assert_eq!(
+            d("2.5")
+                .round(4, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "2.5"
+        );
+        assert_eq!(
+            d("2.5")
+                .rescale(3, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "2.500"
+        );
+        let third = Ratio::new(1, 3).unwrap();
+        assert_eq!(
+            Decimal::from_ratio(third, 4, RoundingMode::HalfEven)
+                .unwrap()
+                .to_string(),
+            "0.3333"
+        );
+        assert_eq!(d("0.125").to_ratio(), Ratio::new(1, 8).unwrap());
+        assert_eq!(d("-19.99").to_f64(), -19.99);
+    }
+
+    #[test]
+    fn allocates_without_losing_a_cent() {
+        let shares: Vec<String> = d("100.00")
+            .allocate(3)
+            .unwrap()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(shares, vec!["33.34", "33.33", "33.33"]);
+        let shares: Vec<String> = d("-100.00")
+            .allocate(3)
+            .unwrap()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(shares, vec!["-33.34", "-33.33", "-33.33"]);
+        let shares: Vec<String> = d("0.05")
+            .allocate_by(&[3, 7])
+            .unwrap()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(shares, vec!["0.02", "0.03"]);
+        let shares: Vec<String> = d("10")
+            .allocate_by(&[1, 0, 2])
+            .unwrap()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(shares, vec!["3", "0", "7"]);
+        assert_eq!(d("1").allocate(0), Err(ArithmeticError::DivideByZero));
+        assert_eq!(
+            d("1").allocate_by(&[0, 0]),
+            Err(ArithmeticError::DivideByZero)
+        );
+
+        // the parts always add back up, and none is more than a unit off its exact share
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..2_000 {
+            let amount = Decimal::new((next() % 2_000_000) as i128 - 1_000_000, 2).unwrap();
+            let weights: Vec<u64> = (0..1 + next() % 7).map(|_| next() % 100).collect();
+            let total: u64 = weights.iter().sum();
+            let shares = match amount.allocate_by(&weights) {
+                Ok(shares) => shares,
+                Err(ArithmeticError::DivideByZero) if total == 0 => continue,
+                Err(e) => panic!("{} by {:?}: {}", amount, weights, e),
+            };
+            let sum = shares
+                .iter()
+                .try_fold(Decimal::zero(), |sum, &s| sum.try_add(s))
+                .unwrap();
+            assert_eq!(sum, amount, "{} by {:?}", amount, weights);
+            for (share, &weight) in shares.iter().zip(&weights) {
+                let exact = amount
+                    .to_ratio()
+                    .try_mul(Ratio::new(weight as i128, total as i128).unwrap())
+                    .unwrap();
+                let off = share.to_ratio().try_sub(exact).unwrap().try_abs().unwrap();
+                assert!(
+                    off < Ratio::new(1, 100).unwrap(),
+                    "{} by {:?}",
+                    amount,
+                    weights
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,643 @@
+// exact fractions, so 1/3 + 1/6 is 1/2 and not 0 the way integer division would have it
+use std::cmp::Ordering;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use chapter_2::checked::{ArithmeticError, CheckedInt, Result};
+use chapter_2::rounding::{divide_rounded, divide_with, RoundingMode};
+
+// always in lowest terms with a positive denominator, so the derived Eq and Hash are by value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ratio<T> {
+    numer: T,
+    denom: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRatioError {
+    pub input: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseRatioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid ratio {:?}: {}", self.input, self.reason)
+    }
+}
+
+impl error::Error for ParseRatioError {}
+
+impl<T: CheckedInt> Ratio<T> {
+    pub fn new(numer: T, denom: T) -> Result<Ratio<T>> {
+        if denom == T::ZERO {
+            return Err(ArithmeticError::DivideByZero);
+        }
+        // the gcd is positive, or MIN when both are 0 or MIN; neither can overflow a division,
+        // and new(i32::MIN, -2) works without ever taking |i32::MIN|
+        let divisor = gcd(numer, denom)?;
+        let (mut numer, mut denom) = (numer.try_div(divisor)?, denom.try_div(divisor)?);
+        if denom.is_negative() {
+            numer = numer.try_neg()?;
+            denom = denom.try_neg()?;
+        }
+        Ok(Ratio { numer, denom })
+    }
+
+    pub fn from_integer(n: T) -> Ratio<T> {
+        Ratio {
+            numer: n,
+            denom: T::ONE,
+        }
+    }
+
+    pub fn zero() -> Ratio<T> {
+        Ratio::from_integer(T::ZERO)
+    }
+
+    pub fn numer(&self) -> T {
+        self.numer
+    }
+
+    pub fn denom(&self) -> T {
+        self.denom
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom == T::ONE
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numer.is_negative()
+    }
+
+    // the gcd of the denominators keeps the intermediate products as small as they can be
+    pub fn try_add(self, other: Ratio<T>) -> Result<Ratio<T>> {
+        let divisor = gcd(self.denom, other.denom)?;
+        let (left, right) = (self.denom.try_div(divisor)?, other.denom.try_div(divisor)?);
+        let numer = self
+            .numer
+            .try_mul(right)?
+            .try_add(other.numer.try_mul(left)?)?;
+        Ratio::new(numer, left.try_mul(other.denom)?)
+    }
+
+    pub fn try_sub(self, other: Ratio<T>) -> Result<Ratio<T>> {
+        let divisor = gcd(self.denom, other.denom)?;
+        let (left, right) = (self.denom.try_div(divisor)?, other.denom.try_div(divisor)?);
+        let numer = self
+            .numer
+            .try_mul(right)?
+            .try_sub(other.numer.try_mul(left)?)?;
+        Ratio::new(numer, left.try_mul(other.denom)?)
+    }
+
+    // cancels across before multiplying, for the same reason
+    pub fn try_mul(self, other: Ratio<T>) -> Result<Ratio<T>> {
+        if self.numer == T::ZERO || other.numer == T::ZERO {
+            return Ok(Ratio::zero());
+        }
+        let a = gcd(self.numer, other.denom)?;
+        let b = gcd(other.numer, self.denom)?;
+        Ratio::new(
+            self.numer.try_div(a)?.try_mul(other.numer.try_div(b)?)?,
+            self.denom.try_div(b)?.try_mul(other.denom.try_div(a)?)?,
+        )
+    }
+
+    pub fn try_div(self, other: Ratio<T>) -> Result<Ratio<T>> {
+        self.try_mul(other.recip()?)
+    }
+
+    pub fn try_neg(self) -> Result<Ratio<T>> {
+        Ok(Ratio {
+            numer: self.numer.try_neg()?,
+            denom: self.denom,
+        })
+    }
+
+    pub fn try_abs(self) -> Result<Ratio<T>> {
+        if self.is_negative() {
+            self.try_neg()
+        } else {
+            Ok(self)
+        }
+    }
+
+    pub fn recip(self) -> Result<Ratio<T>> {
+        Ratio::new(self.denom, self.numer)
+    }
+
+    // the nearest integer by `mode`
+    pub fn divide(self, mode: RoundingMode) -> Result<T> {
+        divide_rounded(self.numer, self.denom, mode)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numer.to_f64() / self.denom.to_f64()
+    }
+
+    // exactly the value of the float, which is always some n / 2^k; None for NaN, infinities
+    // and anything T can't hold
+    pub fn from_f64(x: f64) -> Option<Ratio<T>> {
+        if !x.is_finite() {
+            return None;
+        }
+        let bits = x.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mut mantissa, mut exponent) = if exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | 1 << 52, exponent - 1075)
+        };
+        if mantissa == 0 {
+            return Some(Ratio::zero());
+        }
+        while mantissa & 1 == 0 && exponent < 0 {
+            mantissa >>= 1;
+            exponent += 1;
+        }
+        let mut numer = T::from_f64(mantissa as f64)?;
+        let mut denom = T::ONE;
+        if exponent >= 0 {
+            numer = numer.try_shl(exponent as u32).ok()?;
+        } else {
+            denom = denom.try_shl(exponent.unsigned_abs()).ok()?;
+        }
+        if x < 0.0 {
+            numer = numer.try_neg().ok()?;
+        }
+        Some(Ratio { numer, denom })
+    }
+
+    // [a0; a1, a2, ...] with a0 the floor, so 415/93 is [4; 2, 6, 7]
+    pub fn continued_fraction(self) -> Vec<T> {
+        let mut terms = Vec::new();
+        let (mut numer, mut denom) = (self.numer, self.denom);
+        while denom != T::ZERO {
+            // floor division by a positive denominator never fails and leaves 0 <= rem < denom
+            let (term, rem) = divide_with(numer, denom, RoundingMode::Floor)
+                .expect("the denominator is positive");
+            terms.push(term);
+            numer = denom;
+            denom = rem;
+        }
+        terms
+    }
+
+    pub fn from_continued_fraction(terms: &[T]) -> Result<Ratio<T>> {
+        let (last, rest) = match terms.split_last() {
+            Some(split) => split,
+            None => return Err(ArithmeticError::DivideByZero),
+        };
+        let mut value = Ratio::from_integer(*last);
+        for &term in rest.iter().rev() {
+            value = Ratio::from_integer(term).try_add(value.recip()?)?;
+        }
+        Ok(value)
+    }
+
+    // the closest ratio with a denominator no bigger than `max_denom`
+    pub fn limit_denominator(self, max_denom: T) -> Result<Ratio<T>> {
+        // a is at least as close as b when self is on a's side of their midpoint; this only
+        // adds the two small candidates, where subtracting them from self could overflow
+        best_approximation(&self.continued_fraction(), max_denom, |a, b| {
+            let two = Ratio::from_integer(T::ONE.try_add(T::ONE)?);
+            let middle = a.try_add(b)?.try_div(two)?;
+            Ok(if a <= b {
+                self <= middle
+            } else {
+                self >= middle
+            })
+        })
+    }
+
+    // the closest ratio to a float with a denominator no bigger than `max_denom`, eg. 355/113
+    // for pi under 1000
+    pub fn approximate(x: f64, max_denom: T) -> Option<Ratio<T>> {
+        if !x.is_finite() {
+            return None;
+        }
+        let mut terms = Vec::new();
+        let mut rest = x;
+        // a float's own expansion is finite, but rounding in 1/rest makes up terms past the
+        // first twenty or so; those only matter for denominators beyond f64 precision anyway
+        for _ in 0..64 {
+            let term = rest.floor();
+            // a term too big for T only leads to denominators too big for it
+            match T::from_f64(term) {
+                Some(term) => terms.push(term),
+                None if terms.is_empty() => return None,
+                None => break,
+            }
+            let fraction = rest - term;
+            if fraction == 0.0 {
+                break;
+            }
+            rest = 1.0 / fraction;
+        }
+        best_approximation(&terms, max_denom, |a, b| {
+            Ok((x - a.to_f64()).abs() <= (x - b.to_f64()).abs())
+        })
+        .ok()
+    }
+
+    // `places` decimal places, rounded by `mode`
+    pub fn to_decimal(self, places: u32, mode: RoundingMode) -> Result<String> {
+        let scale = T::from_f64(10.0)
+            .ok_or(ArithmeticError::Overflow)?
+            .try_pow(places)?;
+        let scaled = divide_rounded(self.numer.try_mul(scale)?, self.denom, mode)?;
+        let text = scaled.to_string();
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        let places = places as usize;
+        let digits = format!("{:0>width$}", digits, width = places + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places);
+        Ok(if places == 0 {
+            format!("{}{}", sign, whole)
+        } else {
+            format!("{}{}.{}", sign, whole, fraction)
+        })
+    }
+}
+
+// gcd(a, b) by Euclid, made positive unless it's MIN; only zero when both are. a negative gcd
+// could be -1, and MIN / -1 is the one division that overflows
+fn gcd<T: CheckedInt>(mut a: T, mut b: T) -> Result<T> {
+    while b != T::ZERO {
+        let rem = a.try_rem(b)?;
+        a = b;
+        b = rem;
+    }
+    if a.is_negative() && a != T::MIN {
+        a = a.try_neg()?;
+    }
+    Ok(a)
+}
+
+// walks the convergents of a continued fraction until the next one's denominator is too big,
+// then picks between the last convergent and the best semiconvergent that fits
+fn best_approximation<T, F>(terms: &[T], max_denom: T, at_least_as_close: F) -> Result<Ratio<T>>
+where
+    T: CheckedInt,
+    F: Fn(Ratio<T>, Ratio<T>) -> Result<bool>,
+{
+    if max_denom < T::ONE {
+        return Err(ArithmeticError::DivideByZero);
+    }
+    // h(-2)/k(-2) = 0/1 and h(-1)/k(-1) = 1/0
+    let (mut numer0, mut denom0, mut numer1, mut denom1) = (T::ZERO, T::ONE, T::ONE, T::ZERO);
+    for &term in terms {
+        let denom2 = term.try_mul(denom1).and_then(|d| d.try_add(denom0));
+        if denom2.map_or(true, |d| d > max_denom) {
+            let steps = max_denom.try_sub(denom0)?.try_div(denom1)?;
+            let semiconvergent = Ratio {
+                numer: steps.try_mul(numer1)?.try_add(numer0)?,
+                denom: steps.try_mul(denom1)?.try_add(denom0)?,
+            };
+            let convergent = Ratio {
+                numer: numer1,
+                denom: denom1,
+            };
+            return Ok(if at_least_as_close(convergent, semiconvergent)? {
+                convergent
+            } else {
+                semiconvergent
+            });
+        }
+        let numer2 = term.try_mul(numer1)?.try_add(numer0)?;
+        numer0 = numer1;
+        denom0 = denom1;
+        numer1 = numer2;
+        denom1 = denom2?;
+    }
+    Ok(Ratio {
+        numer: numer1,
+        denom: denom1,
+    })
+}
+
+// compares a/b with c/d without cross-multiplying, which could overflow: compare the integer
+// parts, and if they tie, the reciprocals of the fractional parts the other way round
+fn compare<T: CheckedInt>(a: T, b: T, c: T, d: T) -> Ordering {
+    let (left, left_rem) = divide_with(a, b, RoundingMode::Floor).expect("positive denominator");
+    let (right, right_rem) = divide_with(c, d, RoundingMode::Floor).expect("positive denominator");
+    match left.cmp(&right) {
+        Ordering::Equal => match (left_rem == T::ZERO, right_rem == T::ZERO) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => compare(d, right_rem, b, left_rem),
+        },
+        unequal => unequal,
+    }
+}
+
+impl<T: CheckedInt> Ord for Ratio<T> {
+    fn cmp(&self, other: &Ratio<T>) -> Ordering {
+        compare(self.numer, self.denom, other.numer, other.denom)
+    }
+}
+
+impl<T: CheckedInt> PartialOrd for Ratio<T> {
+    fn partial_cmp(&self, other: &Ratio<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: CheckedInt> fmt::Display for Ratio<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+// "3", "-3/4" or "1.25"
+impl<T: CheckedInt> FromStr for Ratio<T> {
+    type Err = ParseRatioError;
+
+    fn from_str(s: &str) -> ::std::result::Result<Ratio<T>, ParseRatioError> {
+        let error = |reason| ParseRatioError {
+            input: s.to_string(),
+            reason,
+        };
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let digits_only = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        let out_of_range = |_| error("is out of range");
+        if let Some((numer, denom)) = unsigned.split_once('/') {
+            if !digits_only(numer) || !digits_only(denom) {
+                return Err(error("is not a number or n/d ratio"));
+            }
+            let numer = integer(numer, negative).map_err(out_of_range)?;
+            let denom = integer(denom, false).map_err(out_of_range)?;
+            return Ratio::new(numer, denom).map_err(|e| match e {
+                ArithmeticError::DivideByZero => error("has a zero denominator"),
+                _ => error("is out of range"),
+            });
+        }
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if !digits_only(whole) || !(fraction.is_empty() || digits_only(fraction)) {
+            return Err(error("is not a number or n/d ratio"));
+        }
+        let mut all_digits = whole.to_string();
+        all_digits.push_str(fraction);
+        let numer = integer(&all_digits, negative).map_err(out_of_range)?;
+        let denom = T::from_f64(10.0)
+            .ok_or(ArithmeticError::Overflow)
+            .and_then(|ten| ten.try_pow(fraction.len() as u32))
+            .map_err(out_of_range)?;
+        Ratio::new(numer, denom).map_err(out_of_range)
+    }
+}
+
+// accumulates toward the sign it ends up with, so MIN parses
+fn integer<T: CheckedInt>(digits: &str, negative: bool) -> Result<T> {
+    let ten = T::from_f64(10.0).ok_or(ArithmeticError::Overflow)?;
+    let mut n = T::ZERO;
+    for digit in digits.bytes() {
+        let digit = T::from_f64((digit - b'0') as f64).ok_or(ArithmeticError::Overflow)?;
+        n = n.try_mul(ten)?;
+        n = if negative {
+            n.try_sub(digit)?
+        } else {
+            n.try_add(digit)?
+        };
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(numer: i64, denom: i64) -> Ratio<i64> {
+        Ratio::new(numer, denom).unwrap()
+    }
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(r(6, -4), r(-3, 2));
+        assert_eq!((r(6, -4).numer(), r(6, -4).denom()), (-3, 2));
+        assert_eq!(r(0, -7), Ratio::zero());
+        assert_eq!(Ratio::new(1, 0), Err(ArithmeticError::DivideByZero));
+        assert_eq!(Ratio::new(i32::MIN, -2), Ok(Ratio::from_integer(1 << 30)));
+        assert_eq!(Ratio::new(i32::MIN, i32::MIN), Ok(Ratio::from_integer(1)));
+        assert_eq!(Ratio::new(1, i32::MIN), Err(ArithmeticError::Overflow));
+        assert_eq!(Ratio::new(i32::MIN, -1), Err(ArithmeticError::Overflow));
+        assert_eq!(Ratio::new(10u8, 4).unwrap().to_string(), "5/2");
+        let min_over_max = Ratio::new(i32::MIN, i32::MAX).unwrap();
+        assert_eq!(
+            (min_over_max.numer(), min_over_max.denom()),
+            (i32::MIN, i32::MAX)
+        );
+        assert_eq!("-2147483648/2147483647".parse(), Ok(min_over_max));
+        assert_eq!(
+            Ratio::new(i32::MAX, i32::MIN + 1),
+            Ok(Ratio::from_integer(-1))
+        );
+    }
+
+    // every i8 fraction against lowest terms worked out in i128
+    #[test]
+    fn normalizes_every_i8_like_i128() {
+        fn gcd128(a: i128, b: i128) -> i128 {
+            if b == 0 {
+                a.abs()
+            } else {
+                gcd128(b, a % b)
+            }
+        }
+        for a in i8::MIN..=i8::MAX {
+            for b in i8::MIN..=i8::MAX {
+                if b == 0 {
+                    continue;
+                }
+                let (wa, wb) = (a as i128, b as i128);
+                let g = gcd128(wa, wb) * wb.signum();
+                let (n, d) = (wa / g, wb / g);
+                let expected = if n > i8::MAX as i128 || d > i8::MAX as i128 {
+                    Err(ArithmeticError::Overflow)
+                } else {
+                    Ok((n as i8, d as i8))
+                };
+                let ratio = Ratio::new(a, b).map(|r| (r.numer(), r.denom()));
+                assert_eq!(ratio, expected, "{}/{}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn does_exact_arithmetic() {
+        assert_eq!(r(1, 3).try_add(r(1, 6)), Ok(r(1, 2)));
+        assert_eq!(r(1, 3).try_sub(r(1, 2)), Ok(r(-1, 6)));
+        assert_eq!(r(2, 3).try_mul(r(9, 4)), Ok(r(3, 2)));
+        assert_eq!(r(2, 3).try_div(r(4, 9)), Ok(r(3, 2)));
+        assert_eq!(
+            r(2, 3).try_div(Ratio::zero()),
+            Err(ArithmeticError::DivideByZero)
+        );
+        // the cross-cancelling keeps these in range where a naive product wouldn't be
+        let big = r(i64::MAX, 3);
+        assert_eq!(big.try_mul(r(3, i64::MAX)), Ok(Ratio::from_integer(1)));
+        assert_eq!(r(1, i64::MAX).try_add(r(1, i64::MAX)), Ok(r(2, i64::MAX)));
+        assert_eq!(
+            Ratio::from_integer(i64::MAX).try_add(r(1, 1)),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(r(7, 2).divide(RoundingMode::Floor), Ok(3));
+        assert_eq!(r(-7, 2).divide(RoundingMode::HalfEven), Ok(-4));
+    }
+
+    #[test]
+    fn compares_without_overflowing() {
+        assert!(r(1, 3) < r(1, 2));
+        assert!(r(-1, 2) < r(-1, 3));
+        assert!(r(i64::MAX - 2, i64::MAX - 1) < r(i64::MAX - 1, i64::MAX));
+        // against cross-multiplying in i128, over a spread of i32 ratios
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..20_000 {
+            let (a, b) = (next() as i32, (next() as i32 | 1).max(1));
+            let (c, d) = (next() as i32 % 1000, (next() as i32 % 1000).max(1));
+            let (x, y) = (Ratio::new(a, b).unwrap(), Ratio::new(c, d).unwrap());
+            let expected = (a as i128 * d as i128).cmp(&(c as i128 * b as i128));
+            assert_eq!(x.cmp(&y), expected, "{} vs {}", x, y);
+            assert_eq!(x.cmp(&x), Ordering::Equal);
+        }
+    }
+
+    #[test]
+    fn converts_floats_exactly() {
+        assert_eq!(Ratio::from_f64(0.75), Some(r(3, 4)));
+        assert_eq!(Ratio::from_f64(-2.0), Some(r(-2, 1)));
+        assert_eq!(
+            Ratio::<i64>::from_f64(0.1),
+            Some(r(3_602_879_701_896_397, 36_028_797_018_963_968))
+        );
+        assert_eq!(Ratio::<i32>::from_f64(0.1), None);
+        assert_eq!(Ratio::<i64>::from_f64(f64::NAN), None);
+        assert_eq!(Ratio::<u8>::from_f64(-0.5), None);
+        assert_eq!(Ratio::<i64>::from_f64(0.0), Some(Ratio::zero()));
+        for &x in &[0.1, -123.456, 1e-10, 5e15, 1.0 / 3.0] {
+            assert_eq!(Ratio::<i128>::from_f64(x).unwrap().to_f64(), x);
+        }
+        assert_eq!(r(1, 3).to_f64(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn approximates_with_continued_fractions() {
+        assert_eq!(r(415, 93).continued_fraction(), vec![4, 2, 6, 7]);
+        assert_eq!(r(-7, 3).continued_fraction(), vec![-3, 1, 2]);
+        assert_eq!(
+            Ratio::from_continued_fraction(&[4, 2, 6, 7]),
+            Ok(r(415, 93))
+        );
+        assert_eq!(
+            Ratio::approximate(::std::f64::consts::PI, 1000i64),
+            Some(r(355, 113))
+        );
+        assert_eq!(
+            Ratio::approximate(::std::f64::consts::PI, 10),
+            Some(r(22, 7))
+        );
+        assert_eq!(
+            Ratio::approximate(0.333, 10i32),
+            Some(Ratio::new(1, 3).unwrap())
+        );
+        // a tie goes to the convergent, like python's limit_denominator
+        assert_eq!(
+            Ratio::approximate(-0.5, 1i32),
+            Some(Ratio::from_integer(-1))
+        );
+        assert_eq!(Ratio::approximate(2.5, 1i32), Some(Ratio::from_integer(2)));
+        assert_eq!(Ratio::approximate(f64::INFINITY, 10i32), None);
+        // python's Fraction(3141592653589793, 10**15).limit_denominator(n)
+        let pi = r(3_141_592_653_589_793, 1_000_000_000_000_000);
+        assert_eq!(pi.limit_denominator(10), Ok(r(22, 7)));
+        assert_eq!(pi.limit_denominator(100), Ok(r(311, 99)));
+        assert_eq!(pi.limit_denominator(1_000_000), Ok(r(3_126_535, 995_207)));
+        assert_eq!(
+            r(1, 3).limit_denominator(0),
+            Err(ArithmeticError::DivideByZero)
+        );
+        assert_eq!(r(1, 3).limit_denominator(3), Ok(r(1, 3)));
+    }
+
+    #[test]
+    fn reads_and_writes_decimals() {
+        assert_eq!("3/4".parse(), Ok(r(3, 4)));
+        assert_eq!("-6/8".parse(), Ok(r(-3, 4)));
+        assert_eq!("-2.50".parse(), Ok(r(-5, 2)));
+        assert_eq!("+7".parse(), Ok(r(7, 1)));
+        assert_eq!("0.1".parse::<Ratio<u8>>().unwrap().to_string(), "1/10");
+        assert_eq!("-2147483648".parse(), Ok(Ratio::from_integer(i32::MIN)));
+        let error = "1/0".parse::<Ratio<i64>>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid ratio \"1/0\": has a zero denominator"
+        );
+        for bad in &["", "1/", "a", "1.2.3", "1/-2", "1e3", " 1"] {
+            assert!(bad.parse::<Ratio<i64>>().is_err(), "{:?}", bad);
+        }
+        assert_eq!(
+            "-1".parse::<Ratio<u8>>().unwrap_err().reason,
+            "is out of range"
+        );
+        assert_eq!(
+            "256".parse::<Ratio<u8>>().unwrap_err().reason,
+            "is out of range"
+        );
+
+        assert_eq!(
+            r(1, 3).to_decimal(5, RoundingMode::HalfEven),
+            Ok("0.33333".to_string())
+        );
+        assert_eq!(
+            r(2, 3).to_decimal(2, RoundingMode::Floor),
+            Ok("0.66".to_string())
+        );
+        assert_eq!(
+            r(-1, 8).to_decimal(2, RoundingMode::HalfEven),
+            Ok("-0.12".to_string())
+        );
+        assert_eq!(
+            r(-1, 8).to_decimal(2, RoundingMode::HalfAwayFromZero),
+            Ok("-0.13".to_string())
+        );
+        assert_eq!(
+            r(-1, 1000).to_decimal(2, RoundingMode::HalfEven),
+            Ok("0.00".to_string())
+        );
+        assert_eq!(
+            r(7, 2).to_decimal(0, RoundingMode::HalfEven),
+            Ok("4".to_string())
+        );
+        assert_eq!(
+            r(1, 3).to_decimal(30, RoundingMode::Floor),
+            Err(ArithmeticError::Overflow)
+        );
+        for text in &["12.5", "-0.125", "3"] {
+            let ratio: Ratio<i64> = text.parse().unwrap();
+            let places = text.split('.').nth(1).map_or(0, str::len) as u32;
+            assert_eq!(
+                ratio.to_decimal(places, RoundingMode::TowardZero).unwrap(),
+                *text
+            );
+        }
+    }
+}
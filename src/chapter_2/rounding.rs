@@ -0,0 +1,214 @@
+// division that rounds the way it's asked to, rather than always toward zero like `/`
+use chapter_2::checked::{CheckedInt, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoundingMode {
+    // what `/` and divide_safely do
+    TowardZero,
+    // toward negative infinity
+    Floor,
+    // toward positive infinity
+    Ceiling,
+    // to the nearest, ties to the even quotient (banker's rounding)
+    HalfEven,
+    // to the nearest, ties away from zero (school rounding)
+    HalfAwayFromZero,
+    // the remainder is never negative, which makes it a bucket index
+    Euclidean,
+}
+
+pub const ROUNDING_MODES: [RoundingMode; 6] = [
+    RoundingMode::TowardZero,
+    RoundingMode::Floor,
+    RoundingMode::Ceiling,
+    RoundingMode::HalfEven,
+    RoundingMode::HalfAwayFromZero,
+    RoundingMode::Euclidean,
+];
+
+// the quotient and remainder of a / b rounded by `mode`, with a == quotient * b + remainder;
+// an unsigned division that rounds up has a negative remainder, which is an Underflow
+pub fn divide_with<T: CheckedInt>(a: T, b: T, mode: RoundingMode) -> Result<(T, T)> {
+    let (quotient, remainder) = divide(a, b, mode)?;
+    Ok((quotient, remainder?))
+}
+
+// just the quotient, which unsigned types can always have
+pub fn divide_rounded<T: CheckedInt>(a: T, b: T, mode: RoundingMode) -> Result<T> {
+    divide(a, b, mode).map(|(quotient, _)| quotient)
+}
+
+fn divide<T: CheckedInt>(a: T, b: T, mode: RoundingMode) -> Result<(T, Result<T>)> {
+    let quotient = a.try_div(b)?;
+    let remainder = a.try_rem(b)?;
+    if remainder == T::ZERO {
+        return Ok((quotient, Ok(remainder)));
+    }
+    // the remainder has a's sign, so this is whether the exact quotient is below zero
+    let negative = remainder.is_negative() != b.is_negative();
+    // the remainder after one more step away from zero; signed types can't overflow here
+    let away_remainder = if negative {
+        remainder.try_add(b)
+    } else {
+        remainder.try_sub(b)
+    };
+    // how far the exact quotient is from each candidate, in units of 1/|b|
+    let to_zero = magnitude(remainder)?;
+    let to_away = match away_remainder {
+        Ok(away_remainder) => magnitude(away_remainder)?,
+        Err(_) => b.try_sub(remainder)?,
+    };
+    let round_away = match mode {
+        RoundingMode::TowardZero => false,
+        RoundingMode::Floor => negative,
+        RoundingMode::Ceiling => !negative,
+        RoundingMode::Euclidean => remainder.is_negative(),
+        RoundingMode::HalfAwayFromZero => to_away <= to_zero,
+        RoundingMode::HalfEven => {
+            let odd = quotient.try_rem(T::ONE.try_add(T::ONE)?)? != T::ZERO;
+            to_away < to_zero || (to_away == to_zero && odd)
+        }
+    };
+    if !round_away {
+        Ok((quotient, Ok(remainder)))
+    } else if negative {
+        Ok((quotient.try_sub(T::ONE)?, away_remainder))
+    } else {
+        Ok((quotient.try_add(T::ONE)?, away_remainder))
+    }
+}
+
+// |x|, for a remainder, which is always smaller than some divisor and so can't be MIN
+fn magnitude<T: CheckedInt>(x: T) -> Result<T> {
+    if x.is_negative() {
+        x.try_neg()
+    } else {
+        Ok(x)
+    }
+}
+
+// divide_safely, with the reason when it fails
+pub fn divide_checked(a: i32, b: i32) -> Result<i32> {
+    divide_rounded(a, b, RoundingMode::TowardZero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chapter_2::{divide_safely, ArithmeticError};
+
+    // the quotient f64 rounding gives, which is exact for operands this small
+    fn reference(a: i64, b: i64, mode: RoundingMode) -> i64 {
+        let exact = a as f64 / b as f64;
+        (match mode {
+            RoundingMode::TowardZero => exact.trunc(),
+            RoundingMode::Floor => exact.floor(),
+            RoundingMode::Ceiling => exact.ceil(),
+            RoundingMode::HalfEven => exact.round_ties_even(),
+            RoundingMode::HalfAwayFromZero => exact.round(),
+            RoundingMode::Euclidean => return a.div_euclid(b),
+        }) as i64
+    }
+
+    #[test]
+    fn every_small_signed_division() {
+        for a in -60i32..=60 {
+            for b in -60i32..=60 {
+                if b == 0 {
+                    for &mode in &ROUNDING_MODES {
+                        assert_eq!(divide_with(a, b, mode), Err(ArithmeticError::DivideByZero));
+                    }
+                    continue;
+                }
+                for &mode in &ROUNDING_MODES {
+                    let (q, r) = divide_with(a, b, mode).unwrap();
+                    let context = format!("{} / {} {:?}", a, b, mode);
+                    assert_eq!(q as i64, reference(a as i64, b as i64, mode), "{}", context);
+                    assert_eq!(q * b + r, a, "{}", context);
+                    assert!(r.abs() < b.abs(), "{}", context);
+                    match mode {
+                        RoundingMode::Euclidean => assert!(r >= 0, "{}", context),
+                        RoundingMode::Floor if r != 0 => assert_eq!(r > 0, b > 0),
+                        RoundingMode::Ceiling if r != 0 => assert_eq!(r > 0, b < 0),
+                        RoundingMode::HalfEven | RoundingMode::HalfAwayFromZero => {
+                            assert!(2 * r.abs() <= b.abs(), "{}", context)
+                        }
+                        _ => {}
+                    }
+                }
+                assert_eq!(divide_checked(a, b).ok(), divide_safely(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn every_i8_and_u8_division() {
+        for a in i8::MIN..=i8::MAX {
+            for b in i8::MIN..=i8::MAX {
+                for &mode in &ROUNDING_MODES {
+                    let result = divide_with(a, b, mode);
+                    if b == 0 {
+                        assert_eq!(result, Err(ArithmeticError::DivideByZero));
+                    } else if a == i8::MIN && b == -1 {
+                        assert_eq!(result, Err(ArithmeticError::Overflow));
+                    } else {
+                        let q = reference(a as i64, b as i64, mode);
+                        assert_eq!(result.unwrap().0 as i64, q, "{} / {} {:?}", a, b, mode);
+                    }
+                }
+            }
+        }
+        for a in 0..=u8::MAX {
+            for b in 1..=u8::MAX {
+                for &mode in &ROUNDING_MODES {
+                    let q = divide_rounded(a, b, mode).unwrap();
+                    assert_eq!(q as i64, reference(a as i64, b as i64, mode));
+                    match divide_with(a, b, mode) {
+                        Ok((quotient, r)) => {
+                            assert_eq!(quotient, q);
+                            assert_eq!(q as i64 * b as i64 + r as i64, a as i64);
+                        }
+                        Err(e) => {
+                            assert_eq!(e, ArithmeticError::Underflow);
+                            assert!(q as i64 * b as i64 > a as i64);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rounds_the_examples() {
+        assert_eq!(divide_with(8, 3, RoundingMode::TowardZero), Ok((2, 2)));
+        assert_eq!(divide_with(-8, 3, RoundingMode::Floor), Ok((-3, 1)));
+        assert_eq!(divide_with(8, 3, RoundingMode::Ceiling), Ok((3, -1)));
+        assert_eq!(divide_with(5, 2, RoundingMode::HalfEven), Ok((2, 1)));
+        assert_eq!(divide_with(7, 2, RoundingMode::HalfEven), Ok((4, -1)));
+        assert_eq!(
+            divide_with(-5, 2, RoundingMode::HalfAwayFromZero),
+            Ok((-3, 1))
+        );
+        assert_eq!(divide_with(-7, -3, RoundingMode::Euclidean), Ok((3, 2)));
+        assert_eq!(divide_with(-7, 3, RoundingMode::Euclidean), Ok((-3, 2)));
+        // which of 10 buckets a negative hash lands in
+        assert_eq!(
+            divide_with(-13i64, 10, RoundingMode::Euclidean).unwrap().1,
+            7
+        );
+        assert_eq!(
+            divide_rounded(i64::MAX, 2, RoundingMode::HalfEven),
+            Ok(i64::MAX / 2 + 1)
+        );
+        assert_eq!(
+            divide_rounded(u128::MAX, 2, RoundingMode::Ceiling),
+            Ok(u128::MAX / 2 + 1)
+        );
+        assert_eq!(
+            divide_with(8u32, 3, RoundingMode::Ceiling),
+            Err(ArithmeticError::Underflow)
+        );
+        assert_eq!(divide_rounded(8u32, 3, RoundingMode::Ceiling), Ok(3));
+        assert_eq!(divide_checked(i32::MIN, -1), Err(ArithmeticError::Overflow));
+    }
+}
@@ -1,14 +1,86 @@
-use serde_json::{self, Value, Result};
+use std::cmp::Ordering;
+
+use serde_json::{self, Number, Value};
+
+use error::Error;
+
+pub mod binary;
+pub mod canonical;
+pub mod codegen;
+#[macro_use]
+pub mod compare;
+pub mod config;
+pub mod diagnostic;
+pub mod document;
+pub mod exact;
+pub mod formats;
+pub mod jsonpath;
+pub mod limits;
+pub mod ndjson;
+pub mod patch;
+pub mod pointer;
+pub mod printer;
+pub mod schema;
+pub mod stream;
+pub mod template;
+
+pub use self::document::{MeaningDocument, UnknownFields};
+pub use self::jsonpath::JsonPath;
+pub use self::limits::ParseOptions;
+pub use self::patch::Patch;
+pub use self::pointer::{extract, extract_from, FromJson, Pointer};
+pub use self::printer::Printer;
+pub use self::schema::Schema;
+
+// aliased so `Result<Value>` reads the same as it did with serde_json::Result
+pub type Result<T> = ::std::result::Result<T, Error>;
 
 pub fn parse_input_to_json_value(input: &str) -> Result<Value> {
-    serde_json::from_str(input)
+    Ok(serde_json::from_str(input)?)
 }
 
 pub fn get_meaning_of_life(input: &str) -> Result<i64> {
-    match parse_input_to_json_value(input) {
-        Ok(json) => Ok(json["meaningOfLife"].as_i64().unwrap()),
-        Err(e) => Err(e)
+    extract(input, "/meaningOfLife")
+}
+
+// the JSON name for the kind of value, used when reporting a WrongType
+pub fn type_name(value: &Value) -> &'static str {
+    match *value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(ref n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// like Value's PartialEq, except 1 and 1.0 are the same number
+pub fn json_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => compare_numbers(x, y) == Some(Ordering::Equal),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| json_eq(a, b))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, a)| y.get(k).is_some_and(|b| json_eq(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+// orders two numbers without losing precision when both are integers
+pub fn compare_numbers(x: &Number, y: &Number) -> Option<Ordering> {
+    if let (Some(x), Some(y)) = (x.as_i64(), y.as_i64()) {
+        return Some(x.cmp(&y));
     }
+    if let (Some(x), Some(y)) = (x.as_u64(), y.as_u64()) {
+        return Some(x.cmp(&y));
+    }
+    x.as_f64()?.partial_cmp(&y.as_f64()?)
 }
 
 #[cfg(test)]
@@ -29,10 +101,40 @@ mod tests {
     fn test_get_meaning_of_life() {
         match get_meaning_of_life(r#"{"meaningOfLife": 42}"#) {
             Ok(result) => assert_eq!(result, 42),
-            Err(_) => assert!(false)
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn meaning_of_life_from_invalid_json() {
+        match get_meaning_of_life("'asdf'") {
+            Err(Error::Parse(_)) => (),
+            other => panic!("expected a parse error, got {:?}", other),
         }
     }
-}
 
-// let json = parse_input_to_json_value(input)?;
-// Ok(json["meaningOfLife"].as_i64().unwrap())
+    #[test]
+    fn meaning_of_life_missing() {
+        match get_meaning_of_life(r#"{"meaningOfDeath": 42}"#) {
+            Err(Error::MissingKey { path }) => assert_eq!(path, "/meaningOfLife"),
+            other => panic!("expected a missing key, got {:?}", other),
+        }
+        assert!(get_meaning_of_life("42").is_err());
+    }
+
+    #[test]
+    fn meaning_of_life_wrong_type() {
+        match get_meaning_of_life(r#"{"meaningOfLife": "42"}"#) {
+            Err(Error::WrongType { path, expected, found }) => {
+                assert_eq!(path, "/meaningOfLife");
+                assert_eq!(expected, "i64");
+                assert_eq!(found, "string");
+            }
+            other => panic!("expected a wrong type, got {:?}", other),
+        }
+        match get_meaning_of_life(r#"{"meaningOfLife": 4.2}"#) {
+            Err(Error::WrongType { found, .. }) => assert_eq!(found, "number"),
+            other => panic!("expected a wrong type, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,384 @@
+// MessagePack and CBOR, converted to and from the same Value as JSON text; both formats
+// can hold things JSON can't (NaN, binary, non-string keys), and those are errors here
+use serde_json::Value;
+
+use chapter_3::Result;
+use error::Error;
+
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn unrepresentable(format: &'static str, path: &str, what: &str) -> Error {
+    Error::Format {
+        format,
+        reason: format!(
+            "{} at {} has no JSON equivalent",
+            what,
+            if path.is_empty() { "(root)" } else { path }
+        ),
+    }
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn float(format: &'static str, path: &str, f: f64) -> Result<Value> {
+    ::serde_json::Number::from_f64(f)
+        .map(Value::Number)
+        .ok_or_else(|| unrepresentable(format, path, &format!("the float {}", f)))
+}
+
+#[cfg(feature = "msgpack")]
+pub fn to_msgpack(json: &Value) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ::rmpv::encode::write_value(&mut bytes, &json_to_msgpack(json)).map_err(|e| Error::Format {
+        format: "msgpack",
+        reason: e.to_string(),
+    })?;
+    Ok(bytes)
+}
+
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack(bytes: &[u8]) -> Result<Value> {
+    let mut input = bytes;
+    let value = ::rmpv::decode::read_value(&mut input).map_err(|e| Error::Format {
+        format: "msgpack",
+        reason: e.to_string(),
+    })?;
+    if !input.is_empty() {
+        return Err(Error::Format {
+            format: "msgpack",
+            reason: format!("{} trailing byte(s) after the value", input.len()),
+        });
+    }
+    msgpack_to_json(value, "")
+}
+
+#[cfg(feature = "msgpack")]
+fn json_to_msgpack(json: &Value) -> ::rmpv::Value {
+    use rmpv::Value as Msgpack;
+
+    match *json {
+        Value::Null => Msgpack::Nil,
+        Value::Bool(b) => Msgpack::Boolean(b),
+        Value::Number(ref n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Msgpack::from(u),
+            (None, Some(i)) => Msgpack::from(i),
+            _ => Msgpack::F64(n.as_f64().expect("a number is an integer or a float")),
+        },
+        Value::String(ref s) => Msgpack::from(s.as_str()),
+        Value::Array(ref items) => Msgpack::Array(items.iter().map(json_to_msgpack).collect()),
+        Value::Object(ref map) => Msgpack::Map(
+            map.iter()
+                .map(|(k, v)| (Msgpack::from(k.as_str()), json_to_msgpack(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(feature = "msgpack")]
+fn msgpack_to_json(value: ::rmpv::Value, path: &str) -> Result<Value> {
+    use rmpv::Value as Msgpack;
+
+    let unrepresentable = |what: &str| unrepresentable("msgpack", path, what);
+    Ok(match value {
+        Msgpack::Nil => Value::Null,
+        Msgpack::Boolean(b) => Value::Bool(b),
+        Msgpack::Integer(i) => match (i.as_u64(), i.as_i64()) {
+            (Some(u), _) => Value::from(u),
+            (None, Some(i)) => Value::from(i),
+            _ => return Err(unrepresentable("an integer")),
+        },
+        Msgpack::F32(f) => float("msgpack", path, f64::from(f))?,
+        Msgpack::F64(f) => float("msgpack", path, f)?,
+        Msgpack::String(s) => match s.into_str() {
+            Some(s) => Value::String(s),
+            None => return Err(unrepresentable("a string that isn't UTF-8")),
+        },
+        Msgpack::Binary(_) => return Err(unrepresentable("binary data")),
+        Msgpack::Ext(tag, _) => return Err(unrepresentable(&format!("extension type {}", tag))),
+        Msgpack::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| msgpack_to_json(item, &format!("{}/{}", path, i)))
+                .collect::<Result<_>>()?,
+        ),
+        Msgpack::Map(entries) => {
+            let mut map = ::serde_json::Map::new();
+            for (key, item) in entries {
+                let key = match key {
+                    Msgpack::String(s) => s.into_str(),
+                    _ => None,
+                }
+                .ok_or_else(|| unrepresentable("a map key that isn't a string"))?;
+                let item = msgpack_to_json(item, &format!("{}/{}", path, escape(&key)))?;
+                map.insert(key, item);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+#[cfg(feature = "cbor")]
+pub fn to_cbor(json: &Value) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ::ciborium::ser::into_writer(&json_to_cbor(json), &mut bytes).map_err(|e| Error::Format {
+        format: "cbor",
+        reason: e.to_string(),
+    })?;
+    Ok(bytes)
+}
+
+#[cfg(feature = "cbor")]
+pub fn from_cbor(bytes: &[u8]) -> Result<Value> {
+    let mut input = bytes;
+    let value: ::ciborium::value::Value =
+        ::ciborium::de::from_reader(&mut input).map_err(|e| Error::Format {
+            format: "cbor",
+            reason: e.to_string(),
+        })?;
+    if !input.is_empty() {
+        return Err(Error::Format {
+            format: "cbor",
+            reason: format!("{} trailing byte(s) after the value", input.len()),
+        });
+    }
+    cbor_to_json(value, "")
+}
+
+#[cfg(feature = "cbor")]
+fn json_to_cbor(json: &Value) -> ::ciborium::value::Value {
+    use ciborium::value::Value as Cbor;
+
+    match *json {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(b),
+        Value::Number(ref n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Cbor::Integer(u.into()),
+            (None, Some(i)) => Cbor::Integer(i.into()),
+            _ => Cbor::Float(n.as_f64().expect("a number is an integer or a float")),
+        },
+        Value::String(ref s) => Cbor::Text(s.clone()),
+        Value::Array(ref items) => Cbor::Array(items.iter().map(json_to_cbor).collect()),
+        Value::Object(ref map) => Cbor::Map(
+            map.iter()
+                .map(|(k, v)| (Cbor::Text(k.clone()), json_to_cbor(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_to_json(value: ::ciborium::value::Value, path: &str) -> Result<Value> {
+    use ciborium::value::Value as Cbor;
+    use std::convert::TryFrom;
+
+    let unrepresentable = |what: &str| unrepresentable("cbor", path, what);
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let i = i128::from(i);
+            if let Ok(u) = u64::try_from(i) {
+                Value::from(u)
+            } else if let Ok(i) = i64::try_from(i) {
+                Value::from(i)
+            } else {
+                return Err(unrepresentable(&format!("the integer {}", i)));
+            }
+        }
+        Cbor::Float(f) => float("cbor", path, f)?,
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(_) => return Err(unrepresentable("a byte string")),
+        Cbor::Tag(tag, _) => return Err(unrepresentable(&format!("tag {}", tag))),
+        Cbor::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| cbor_to_json(item, &format!("{}/{}", path, i)))
+                .collect::<Result<_>>()?,
+        ),
+        Cbor::Map(entries) => {
+            let mut map = ::serde_json::Map::new();
+            for (key, item) in entries {
+                let key = match key {
+                    Cbor::Text(s) => s,
+                    _ => return Err(unrepresentable("a map key that isn't a string")),
+                };
+                let item = cbor_to_json(item, &format!("{}/{}", path, escape(&key)))?;
+                map.insert(key, item);
+            }
+            Value::Object(map)
+        }
+        _ => return Err(unrepresentable("an unknown CBOR value")),
+    })
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(not(feature = "msgpack"))]
+pub fn to_msgpack(_json: &Value) -> Result<Vec<u8>> {
+    Err(Error::UnsupportedFormat { format: "msgpack" })
+}
+
+#[cfg(not(feature = "msgpack"))]
+pub fn from_msgpack(_bytes: &[u8]) -> Result<Value> {
+    Err(Error::UnsupportedFormat { format: "msgpack" })
+}
+
+#[cfg(not(feature = "cbor"))]
+pub fn to_cbor(_json: &Value) -> Result<Vec<u8>> {
+    Err(Error::UnsupportedFormat { format: "cbor" })
+}
+
+#[cfg(not(feature = "cbor"))]
+pub fn from_cbor(_bytes: &[u8]) -> Result<Value> {
+    Err(Error::UnsupportedFormat { format: "cbor" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "msgpack", feature = "cbor"))]
+    fn samples() -> Vec<Value> {
+        vec![
+            json!(null),
+            json!(true),
+            json!(0),
+            json!(-1),
+            json!(255),
+            json!(65536),
+            json!(i64::MIN),
+            json!(i64::MAX),
+            json!(u64::MAX),
+            json!(0.1),
+            json!(1.0),
+            json!(-2.5e-300),
+            json!(f64::MAX),
+            json!(""),
+            json!("héllo wörld 😀"),
+            json!("x".repeat(70_000)),
+            json!([]),
+            json!({}),
+            json!({"meaningOfLife": 42, "nested": {"list": [1, "two", 3.5, null, [false]]}, "a/b~c": {}}),
+            Value::Array((0..20).map(Value::from).collect()),
+        ]
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trips_losslessly() {
+        for sample in samples() {
+            let bytes = to_msgpack(&sample).unwrap();
+            assert_eq!(from_msgpack(&bytes).unwrap(), sample);
+        }
+        // the smallest encodings are used, eg. a positive fixint and a fixmap
+        assert_eq!(to_msgpack(&json!(42)).unwrap(), vec![0x2a]);
+        assert_eq!(
+            to_msgpack(&json!({"a": 1})).unwrap(),
+            vec![0x81, 0xa1, b'a', 0x01]
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_rejects_what_json_cannot_hold() {
+        let reason = |bytes: &[u8]| match from_msgpack(bytes) {
+            Err(Error::Format {
+                format: "msgpack",
+                reason,
+            }) => reason,
+            other => panic!("{:?} should be rejected, got {:?}", bytes, other),
+        };
+        // f64 NaN
+        assert_eq!(
+            reason(&[0xcb, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0]),
+            "the float NaN at (root) has no JSON equivalent"
+        );
+        // {1: "a"}
+        assert_eq!(
+            reason(&[0x81, 0x01, 0xa1, b'a']),
+            "a map key that isn't a string at (root) has no JSON equivalent"
+        );
+        // {"k": [bin8 of one byte]}
+        assert_eq!(
+            reason(&[0x81, 0xa1, b'k', 0x91, 0xc4, 0x01, 0x00]),
+            "binary data at /k/0 has no JSON equivalent"
+        );
+        // fixext1
+        assert!(reason(&[0xd4, 0x01, 0x00]).contains("extension type 1"));
+        // truncated and trailing input
+        reason(&[0x92, 0x01]);
+        assert!(reason(&[0x01, 0x02]).contains("trailing"));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trips_losslessly() {
+        for sample in samples() {
+            let bytes = to_cbor(&sample).unwrap();
+            assert_eq!(from_cbor(&bytes).unwrap(), sample);
+        }
+        // RFC 8949 appendix A examples
+        assert_eq!(to_cbor(&json!(100)).unwrap(), vec![0x18, 0x64]);
+        assert_eq!(to_cbor(&json!(-1)).unwrap(), vec![0x20]);
+        assert_eq!(to_cbor(&json!("a")).unwrap(), vec![0x61, 0x61]);
+        assert_eq!(
+            to_cbor(&json!([1, [2, 3]])).unwrap(),
+            vec![0x82, 0x01, 0x82, 0x02, 0x03]
+        );
+        assert_eq!(from_cbor(&[0xf9, 0x3c, 0x00]).unwrap(), json!(1.0));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_rejects_what_json_cannot_hold() {
+        let reason = |bytes: &[u8]| match from_cbor(bytes) {
+            Err(Error::Format {
+                format: "cbor",
+                reason,
+            }) => reason,
+            other => panic!("{:?} should be rejected, got {:?}", bytes, other),
+        };
+        // half-precision NaN and Infinity
+        assert!(reason(&[0xf9, 0x7e, 0x00]).contains("NaN"));
+        assert!(reason(&[0xf9, 0x7c, 0x00]).contains("inf"));
+        // {1: 2}
+        assert_eq!(
+            reason(&[0xa1, 0x01, 0x02]),
+            "a map key that isn't a string at (root) has no JSON equivalent"
+        );
+        // [h'01']
+        assert_eq!(
+            reason(&[0x81, 0x41, 0x01]),
+            "a byte string at /0 has no JSON equivalent"
+        );
+        // -18446744073709551616
+        assert!(
+            reason(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+                .contains("-18446744073709551616")
+        );
+        // 1(1363896240), an epoch timestamp tag
+        assert!(reason(&[0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]).contains("tag 1"));
+        assert!(reason(&[0x01, 0x02]).contains("trailing"));
+    }
+
+    #[cfg(not(feature = "msgpack"))]
+    #[test]
+    fn msgpack_needs_its_feature() {
+        assert!(matches!(
+            to_msgpack(&json!(1)),
+            Err(Error::UnsupportedFormat { format: "msgpack" })
+        ));
+    }
+
+    #[cfg(not(feature = "cbor"))]
+    #[test]
+    fn cbor_needs_its_feature() {
+        assert!(matches!(
+            from_cbor(&[0x01]),
+            Err(Error::UnsupportedFormat { format: "cbor" })
+        ));
+    }
+}
@@ -0,0 +1,298 @@
+// RFC 8785 (JCS) canonical form, so the same document always hashes and signs the same
+use std::cmp::Ordering;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use chapter_3::{parse_input_to_json_value, Result};
+use error::Error;
+
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, &mut out);
+    out
+}
+
+pub fn canonicalize_input(input: &str) -> Result<String> {
+    Ok(canonicalize(&parse_input_to_json_value(input)?))
+}
+
+// SHA-256 of the canonical form
+pub fn digest(value: &Value) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&Sha256::digest(canonicalize(value).as_bytes()));
+    hash
+}
+
+// the digest as lowercase hex, for logs and filenames
+pub fn content_hash(value: &Value) -> String {
+    digest(value).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match *value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+        // JCS numbers are doubles, so integers past 2^53 round just like they would in JavaScript
+        Value::Number(ref n) => out.push_str(
+            &canonical_number(n.as_f64().expect("serde_json numbers are finite"))
+                .expect("serde_json numbers are finite"),
+        ),
+        Value::String(ref s) => write_string(s, out),
+        Value::Array(ref items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(ref map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| utf16_cmp(a.0, b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+// keys are ordered by UTF-16 code units, not by bytes or chars
+fn utf16_cmp(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+// JSON.stringify's escaping: the short escapes where they exist, \u00xx for other controls
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// ECMAScript's Number.prototype.toString: the shortest digits that round-trip,
+// laid out plainly between 1e-7 and 1e21 and in exponent form outside that
+pub fn canonical_number(f: f64) -> Result<String> {
+    if !f.is_finite() {
+        return Err(Error::Format {
+            format: "jcs",
+            reason: format!("{} has no JSON equivalent", f),
+        });
+    }
+    if f == 0.0 {
+        return Ok("0".to_string());
+    }
+    // {:e} gives the shortest round-trip digits, eg. "-3.3333333333333335e-6"
+    let exp_form = format!("{:e}", f.abs());
+    let (mantissa, exponent) = exp_form.split_at(exp_form.find('e').unwrap());
+    let mut digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let k = digits.len() as i32;
+    // {:e} rounds an exact tie up, while ECMAScript wants the even last digit; a tie means f
+    // is exactly the k + 1 digit decimal halfway between the two, which is rare enough to check
+    // only when the even neighbour round-trips too
+    let last = digits.as_bytes()[digits.len() - 1];
+    if last % 2 == 1 {
+        let even = format!("{}{}", &digits[..digits.len() - 1], (last - 1) as char);
+        let round_trips = format!("{}e{}", even, exponent - (k - 1)).parse() == Ok(f.abs());
+        if round_trips && is_double(format!("{}5", even).parse().unwrap(), exponent - k) {
+            digits = even;
+        }
+    }
+    // the decimal point sits after n digits
+    let n = exponent + 1;
+
+    let mut out = String::new();
+    if f < 0.0 {
+        out.push('-');
+    }
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if n > 0 { '+' } else { '-' });
+        out.push_str(&(n - 1).abs().to_string());
+    }
+    Ok(out)
+}
+
+// whether n * 10^p is exactly some double, ie. its odd part fits the 53-bit significand;
+// only asked of values that already parse to a double, so the exponent is never out of range
+fn is_double(mut n: u128, p: i32) -> bool {
+    if p >= 0 {
+        match 5u128
+            .checked_pow(p as u32)
+            .and_then(|power| n.checked_mul(power))
+        {
+            Some(scaled) => n = scaled,
+            None => return false,
+        }
+    } else {
+        for _ in 0..-p {
+            if !n.is_multiple_of(5) {
+                return false;
+            }
+            n /= 5;
+        }
+    }
+    n >> n.trailing_zeros() < 1 << 53
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_numbers_like_ecmascript() {
+        // RFC 8785 appendix B, as IEEE 754 bit patterns
+        let cases = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for &(bits, expected) in cases.iter() {
+            assert_eq!(
+                canonical_number(f64::from_bits(bits)).unwrap(),
+                expected,
+                "{:016x}",
+                bits
+            );
+        }
+        // exact ties between two shortest forms, where {:e} would have rounded the last digit up
+        for &(bits, rounded_up, expected) in &[
+            (
+                0x431e9023362072a9,
+                "2.1506825519177383e15",
+                "2150682551917738.2",
+            ),
+            (
+                0x427a7fea192eb880,
+                "1.8210431679795313e12",
+                "1821043167979.5312",
+            ),
+        ] {
+            let f = f64::from_bits(bits);
+            assert_eq!(format!("{:e}", f), rounded_up);
+            assert_eq!(canonical_number(f).unwrap(), expected);
+        }
+        for bits in [0x7fffffffffffffff_u64, 0x7ff0000000000000] {
+            assert!(canonical_number(f64::from_bits(bits)).is_err());
+        }
+    }
+
+    #[test]
+    fn canonicalizes_the_rfc_example() {
+        // RFC 8785 section 3.2.2
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            canonicalize_input(input).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn sorts_keys_by_utf16_code_units() {
+        // RFC 8785 section 3.2.3; the emoji's surrogates sort before U+FB33
+        let value = json!({
+            "\u{20ac}": "Euro Sign",
+            "\r": "Carriage Return",
+            "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\u{1f600}": "Emoji: Grinning Face",
+            "\u{80}": "Control",
+            "\u{f6}": "Latin Small Letter O With Diaeresis"
+        });
+        let canonical = canonicalize(&value);
+        let positions: Vec<usize> = [
+            "\\r",
+            "1",
+            "\u{80}",
+            "\u{f6}",
+            "\u{20ac}",
+            "\u{1f600}",
+            "\u{fb33}",
+        ]
+        .iter()
+        .map(|k| canonical.find(&format!("\"{}\":", k)).unwrap())
+        .collect();
+        let mut sorted = positions.clone();
+        sorted.sort();
+        assert_eq!(positions, sorted);
+    }
+
+    #[test]
+    fn hashes_the_canonical_form() {
+        let hash = content_hash(&json!({"meaningOfLife": 42}));
+        assert_eq!(
+            hash,
+            "edbff8147137fbe9a204fe3fbd00a88f8beaa9133e13f70bd5137dac2a73dd38"
+        );
+        // whitespace, key order and number spelling don't change the hash
+        let same = parse_input_to_json_value("{ \"meaningOfLife\" : 42.0 }").unwrap();
+        assert_eq!(content_hash(&same), hash);
+        assert_ne!(content_hash(&json!({"meaningOfLife": 43})), hash);
+        assert_eq!(
+            canonicalize(&json!({"b": [1, {"d": 2, "c": 1}], "a": "\u{1f}"})),
+            r#"{"a":"\u001f","b":[1,{"c":1,"d":2}]}"#
+        );
+    }
+}
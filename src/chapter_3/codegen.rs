@@ -0,0 +1,478 @@
+// infers the shape of some sample documents and writes it out as serde structs, so a payload
+// can be read with serde_json::from_str::<Root> instead of poking at a Value
+use std::collections::{BTreeMap, HashSet};
+
+use serde_json::Value;
+
+use chapter_3::{type_name, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    // only nulls or empty arrays seen so far
+    Unknown,
+    Bool,
+    Integer,
+    Float,
+    String,
+    Array(Box<Shape>),
+    Object(BTreeMap<String, Field>),
+    // the samples disagree, eg. a string in one and an object in another
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub kind: Kind,
+    // null in at least one sample
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub shape: Shape,
+    // missing from at least one sample
+    pub optional: bool,
+}
+
+impl Shape {
+    fn unknown() -> Shape {
+        Shape {
+            kind: Kind::Unknown,
+            nullable: false,
+        }
+    }
+
+    pub fn of(value: &Value) -> Shape {
+        let kind = match *value {
+            Value::Null => {
+                return Shape {
+                    kind: Kind::Unknown,
+                    nullable: true,
+                }
+            }
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(ref n) if n.is_i64() => Kind::Integer,
+            // u64s past i64::MAX don't fit the i64 we'd generate
+            Value::Number(_) => Kind::Float,
+            Value::String(_) => Kind::String,
+            Value::Array(ref items) => Kind::Array(Box::new(
+                items
+                    .iter()
+                    .fold(Shape::unknown(), |shape, item| shape.merge(Shape::of(item))),
+            )),
+            Value::Object(ref map) => Kind::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        let field = Field {
+                            shape: Shape::of(value),
+                            optional: false,
+                        };
+                        (key.clone(), field)
+                    })
+                    .collect(),
+            ),
+        };
+        Shape {
+            kind,
+            nullable: false,
+        }
+    }
+
+    // the narrowest shape that both shapes fit
+    pub fn merge(self, other: Shape) -> Shape {
+        let kind = match (self.kind, other.kind) {
+            (Kind::Unknown, kind) | (kind, Kind::Unknown) => kind,
+            (Kind::Integer, Kind::Float) | (Kind::Float, Kind::Integer) => Kind::Float,
+            (Kind::Array(a), Kind::Array(b)) => Kind::Array(Box::new(a.merge(*b))),
+            (Kind::Object(mut a), Kind::Object(mut b)) => {
+                for (key, field) in a.iter_mut() {
+                    match b.remove(key) {
+                        Some(other) => {
+                            let shape = ::std::mem::replace(&mut field.shape, Shape::unknown());
+                            field.shape = shape.merge(other.shape);
+                            field.optional |= other.optional;
+                        }
+                        None => field.optional = true,
+                    }
+                }
+                for (key, mut field) in b {
+                    field.optional = true;
+                    a.insert(key, field);
+                }
+                Kind::Object(a)
+            }
+            (a, b) => {
+                if a == b {
+                    a
+                } else {
+                    Kind::Any
+                }
+            }
+        };
+        Shape {
+            kind,
+            nullable: self.nullable || other.nullable,
+        }
+    }
+}
+
+// every sample has to be an object; they're merged into one shape for the root struct
+pub fn infer(samples: &[Value]) -> Result<Shape> {
+    let mut shape = Shape {
+        kind: Kind::Object(BTreeMap::new()),
+        nullable: false,
+    };
+    for (i, sample) in samples.iter().enumerate() {
+        if !sample.is_object() {
+            return Err(Error::WrongType {
+                path: format!("sample {}", i + 1),
+                expected: "object",
+                found: type_name(sample),
+            });
+        }
+        shape = if i == 0 {
+            Shape::of(sample)
+        } else {
+            shape.merge(Shape::of(sample))
+        };
+    }
+    Ok(shape)
+}
+
+// Rust source for the root struct `name` and one struct per nested object, root first
+pub fn rust_types(samples: &[Value], name: &str) -> Result<String> {
+    let shape = infer(samples)?;
+    let mut generator = Generator {
+        structs: Vec::new(),
+        names: RESERVED.iter().map(|name| name.to_string()).collect(),
+    };
+    let root = generator.fresh_name(&pascal_case(name));
+    generator.write_struct(&root, &shape);
+    let mut out = "use serde::{Deserialize, Serialize};\n".to_string();
+    for source in &generator.structs {
+        out.push('\n');
+        out.push_str(source);
+    }
+    Ok(out)
+}
+
+// names the generated code already uses, which a struct of the same name would shadow
+const RESERVED: &[&str] = &[
+    "Self",
+    "Option",
+    "String",
+    "Vec",
+    "Box",
+    "Serialize",
+    "Deserialize",
+];
+
+struct Generator {
+    // finished struct definitions, in the order they were started
+    structs: Vec<String>,
+    names: HashSet<String>,
+}
+
+impl Generator {
+    fn write_struct(&mut self, name: &str, shape: &Shape) {
+        let index = self.structs.len();
+        self.structs.push(String::new());
+        let mut source = format!(
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\npub struct {} {{\n",
+            name
+        );
+        if let Kind::Object(ref fields) = shape.kind {
+            // "aB" and "a_b" are both a_b, so the second becomes a_b2
+            let mut idents = HashSet::new();
+            for (key, field) in fields {
+                let ident = fresh(&snake_case(key), &mut idents);
+                let mut attributes = Vec::new();
+                if ident != *key {
+                    attributes.push(format!("rename = {:?}", key));
+                }
+                let mut rust_type = self.rust_type(key, &field.shape);
+                if field.optional {
+                    attributes.push("default".to_string());
+                    attributes.push("skip_serializing_if = \"Option::is_none\"".to_string());
+                    if !field.shape.nullable {
+                        rust_type = format!("Option<{}>", rust_type);
+                    }
+                }
+                if !attributes.is_empty() {
+                    source.push_str(&format!("    #[serde({})]\n", attributes.join(", ")));
+                }
+                source.push_str(&format!("    pub {}: {},\n", ident, rust_type));
+            }
+        }
+        source.push_str("}\n");
+        self.structs[index] = source;
+    }
+
+    fn rust_type(&mut self, key: &str, shape: &Shape) -> String {
+        let rust_type = match shape.kind {
+            Kind::Unknown | Kind::Any => "serde_json::Value".to_string(),
+            Kind::Bool => "bool".to_string(),
+            Kind::Integer => "i64".to_string(),
+            Kind::Float => "f64".to_string(),
+            Kind::String => "String".to_string(),
+            Kind::Array(ref items) => format!("Vec<{}>", self.rust_type(&singular(key), items)),
+            Kind::Object(_) => {
+                let name = self.fresh_name(&pascal_case(key));
+                self.write_struct(&name, shape);
+                name
+            }
+        };
+        if shape.nullable {
+            format!("Option<{}>", rust_type)
+        } else {
+            rust_type
+        }
+    }
+
+    // "Author", then "Author2" if two different objects are both called author
+    fn fresh_name(&mut self, base: &str) -> String {
+        fresh(base, &mut self.names)
+    }
+}
+
+// base, or base2, base3... for the first that isn't taken yet, which it then takes
+fn fresh(base: &str, taken: &mut HashSet<String>) -> String {
+    let mut name = base.to_string();
+    let mut n = 1;
+    while taken.contains(&name) {
+        n += 1;
+        name = format!("{}{}", base, n);
+    }
+    taken.insert(name.clone());
+    name
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "try", "typeof", "unsized", "virtual", "yield",
+];
+
+// splits "meaningOfLife", "meaning_of_life", "meaning-of-life" and "HTTPStatus" into lowercase words
+fn words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(word.split_off(0));
+            }
+            continue;
+        }
+        let previous = if i > 0 { chars[i - 1] } else { ' ' };
+        let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+        let boundary = c.is_uppercase()
+            && (previous.is_lowercase()
+                || previous.is_numeric()
+                || (previous.is_uppercase() && next_is_lower));
+        if boundary && !word.is_empty() {
+            words.push(word.split_off(0));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// a key without any letters or digits, eg. "" or "-", is just a field
+fn snake_case(key: &str) -> String {
+    let mut ident = words(key).join("_");
+    if ident.is_empty() {
+        return "field".to_string();
+    }
+    if ident.starts_with(|c: char| c.is_numeric()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn pascal_case(key: &str) -> String {
+    let mut name: String = words(key)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_numeric()) {
+        name.insert_str(0, "Item");
+    }
+    name
+}
+
+// the struct for the items of "authors" is Author
+fn singular(key: &str) -> String {
+    if key.len() > 1 && key.ends_with('s') && !key.ends_with("ss") {
+        key[..key.len() - 1].to_string()
+    } else {
+        format!("{}Item", key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_samples() {
+        let shape = infer(&[
+            json!({"meaningOfLife": 42, "ratio": 1, "note": null, "tags": []}),
+            json!({"meaningOfLife": 7, "ratio": 0.5, "note": "hi", "author": "Douglas", "tags": ["a"]}),
+        ])
+        .unwrap();
+        let fields = match shape.kind {
+            Kind::Object(fields) => fields,
+            other => panic!("expected an object, got {:?}", other),
+        };
+        assert_eq!(fields["meaningOfLife"].shape.kind, Kind::Integer);
+        assert_eq!(fields["ratio"].shape.kind, Kind::Float);
+        assert_eq!(
+            fields["note"].shape,
+            Shape {
+                kind: Kind::String,
+                nullable: true
+            }
+        );
+        assert!(!fields["note"].optional);
+        assert!(fields["author"].optional);
+        assert_eq!(
+            fields["tags"].shape.kind,
+            Kind::Array(Box::new(Shape::of(&json!("a"))))
+        );
+        assert_eq!(
+            Shape::of(&json!(1)).merge(Shape::of(&json!("1"))).kind,
+            Kind::Any
+        );
+    }
+
+    #[test]
+    fn writes_serde_structs() {
+        let samples = [
+            json!({
+                "meaningOfLife": 42,
+                "author": {"name": "Douglas", "born": 1952},
+                "books": [{"title": "Mostly Harmless", "year": 1992}],
+                "type": "novel",
+                "rating": null
+            }),
+            json!({
+                "meaningOfLife": 42,
+                "books": [{"title": "Life, the Universe and Everything", "year": 1982.5}],
+                "type": "novel",
+                "rating": 4.5
+            }),
+        ];
+        assert_eq!(
+            rust_types(&samples, "root").unwrap(),
+            "use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    #[serde(default, skip_serializing_if = \"Option::is_none\")]
+    pub author: Option<Author>,
+    pub books: Vec<Book>,
+    #[serde(rename = \"meaningOfLife\")]
+    pub meaning_of_life: i64,
+    pub rating: Option<f64>,
+    #[serde(rename = \"type\")]
+    pub type_: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Author {
+    pub born: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Book {
+    pub title: String,
+    pub year: f64,
+}
+"
+        );
+    }
+
+    #[test]
+    fn names_things_legally() {
+        assert_eq!(snake_case("meaningOfLife"), "meaning_of_life");
+        assert_eq!(snake_case("HTTPStatus"), "http_status");
+        assert_eq!(snake_case("content-type"), "content_type");
+        assert_eq!(snake_case("2fa"), "_2fa");
+        assert_eq!(snake_case("self"), "self_");
+        assert_eq!(snake_case("-"), "field");
+        assert_eq!(pascal_case("user_profile"), "UserProfile");
+        assert_eq!(pascal_case("42"), "Item42");
+        assert_eq!(singular("address"), "addressItem");
+
+        let source = rust_types(&[json!({"a": {"x": 1}, "b": {"a": {"y": 2}}})], "A").unwrap();
+        assert!(
+            source.contains("pub struct A {\n    pub a: A2,\n    pub b: B,"),
+            "{}",
+            source
+        );
+        assert!(
+            source.contains("pub struct A3 {\n    pub y: i64,"),
+            "{}",
+            source
+        );
+    }
+
+    // checked in and built with the tests, so the compiler and serde's derives both vouch for
+    // what rust_types writes; the derives here come from serde_derive, leaving the import unused
+    #[allow(dead_code, unused_imports)]
+    mod generated {
+        include!("../../tests/fixtures/codegen_names.rs");
+    }
+
+    #[test]
+    fn writes_code_that_compiles() {
+        let sample = json!({
+            "string": {"a": "x"},
+            "aB": 1,
+            "a_b": 2,
+            "-": 3,
+            "": 4,
+            "self": {"k": 1},
+            "self_": true,
+            "strings": [{"b": null}]
+        });
+        assert_eq!(
+            rust_types(::std::slice::from_ref(&sample), "self").unwrap(),
+            include_str!("../../tests/fixtures/codegen_names.rs")
+        );
+        let typed: generated::Self2 = ::serde_json::from_value(sample.clone()).unwrap();
+        assert_eq!(typed.a_b2, 2);
+        assert_eq!(::serde_json::to_value(&typed).unwrap(), sample);
+    }
+
+    #[test]
+    fn wants_objects() {
+        match rust_types(&[json!({}), json!([1])], "Root") {
+            Err(Error::WrongType { path, found, .. }) => {
+                assert_eq!((path.as_str(), found), ("sample 2", "array"))
+            }
+            other => panic!("expected WrongType, got {:?}", other),
+        }
+        assert!(rust_types(&[], "Root")
+            .unwrap()
+            .ends_with("pub struct Root {\n}\n"));
+    }
+}
@@ -0,0 +1,358 @@
+// structural comparison of two values, rendered as a unified-style report for test failures
+use std::collections::HashSet;
+use std::fmt;
+
+use serde_json::{self, Value};
+
+use chapter_3::{json_eq, Pointer};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        path: Pointer,
+        value: Value,
+    },
+    Removed {
+        path: Pointer,
+        value: Value,
+    },
+    Changed {
+        path: Pointer,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &Pointer {
+        match *self {
+            Change::Added { ref path, .. }
+            | Change::Removed { ref path, .. }
+            | Change::Changed { ref path, .. } => path,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    ignore_array_order: bool,
+    ignored_keys: HashSet<String>,
+    ignored_paths: HashSet<Pointer>,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    // treat arrays as multisets, so [1, 2] and [2, 1] compare equal
+    pub fn ignore_array_order(mut self, ignore: bool) -> Options {
+        self.ignore_array_order = ignore;
+        self
+    }
+
+    // skip an object key wherever it appears, eg. "updatedAt"
+    pub fn ignore_key<S: Into<String>>(mut self, key: S) -> Options {
+        self.ignored_keys.insert(key.into());
+        self
+    }
+
+    // skip one location, eg. "/meta/requestId"
+    pub fn ignore_path(mut self, path: Pointer) -> Options {
+        self.ignored_paths.insert(path);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Comparison {
+    pub changes: Vec<Change>,
+}
+
+pub fn compare(expected: &Value, actual: &Value, options: &Options) -> Comparison {
+    let mut changes = Vec::new();
+    compare_at(&Pointer::root(), expected, actual, options, &mut changes);
+    Comparison { changes }
+}
+
+fn compare_at(path: &Pointer, a: &Value, b: &Value, options: &Options, changes: &mut Vec<Change>) {
+    if options.ignored_paths.contains(path) {
+        return;
+    }
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a
+                .keys()
+                .chain(b.keys().filter(|k| !a.contains_key(*k)))
+                .collect();
+            keys.sort();
+            for key in keys {
+                if options.ignored_keys.contains(key) {
+                    continue;
+                }
+                let child = path.child(key.as_str());
+                match (a.get(key), b.get(key)) {
+                    (Some(old), Some(new)) => compare_at(&child, old, new, options, changes),
+                    (Some(old), None) if !options.ignored_paths.contains(&child) => {
+                        changes.push(Change::Removed {
+                            path: child,
+                            value: old.clone(),
+                        })
+                    }
+                    (None, Some(new)) if !options.ignored_paths.contains(&child) => {
+                        changes.push(Change::Added {
+                            path: child,
+                            value: new.clone(),
+                        })
+                    }
+                    _ => (),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if options.ignore_array_order => {
+            compare_unordered(path, a, b, options, changes)
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (old, new)) in a.iter().zip(b).enumerate() {
+                compare_at(&path.child(i.to_string()), old, new, options, changes);
+            }
+            for (i, old) in a.iter().enumerate().skip(b.len()) {
+                changes.push(Change::Removed {
+                    path: path.child(i.to_string()),
+                    value: old.clone(),
+                });
+            }
+            for (i, new) in b.iter().enumerate().skip(a.len()) {
+                changes.push(Change::Added {
+                    path: path.child(i.to_string()),
+                    value: new.clone(),
+                });
+            }
+        }
+        _ if json_eq(a, b) => (),
+        _ => changes.push(Change::Changed {
+            path: path.clone(),
+            old: a.clone(),
+            new: b.clone(),
+        }),
+    }
+}
+
+// pairs up elements that are equal under the same options; whatever is left over was added or removed
+fn compare_unordered(
+    path: &Pointer,
+    a: &[Value],
+    b: &[Value],
+    options: &Options,
+    changes: &mut Vec<Change>,
+) {
+    let mut unmatched: Vec<usize> = (0..b.len()).collect();
+    let mut removed = Vec::new();
+    for (i, old) in a.iter().enumerate() {
+        let found = unmatched.iter().position(|&j| {
+            let mut nested = Vec::new();
+            compare_at(&path.child(i.to_string()), old, &b[j], options, &mut nested);
+            nested.is_empty()
+        });
+        match found {
+            Some(position) => {
+                unmatched.remove(position);
+            }
+            None => removed.push(i),
+        }
+    }
+    for i in removed {
+        changes.push(Change::Removed {
+            path: path.child(i.to_string()),
+            value: a[i].clone(),
+        });
+    }
+    for j in unmatched {
+        changes.push(Change::Added {
+            path: path.child(j.to_string()),
+            value: b[j].clone(),
+        });
+    }
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Comparison {
+    pub fn is_equal(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let mut report = String::new();
+        report.push_str(&paint(BOLD, "--- expected"));
+        report.push('\n');
+        report.push_str(&paint(BOLD, "+++ actual"));
+        report.push('\n');
+        for change in &self.changes {
+            let path = change.path().to_string();
+            let path = if path.is_empty() {
+                "(root)".to_string()
+            } else {
+                path
+            };
+            report.push_str(&paint(CYAN, &format!("@@ {} @@", path)));
+            report.push('\n');
+            let (old, new) = match *change {
+                Change::Added { ref value, .. } => (None, Some(value)),
+                Change::Removed { ref value, .. } => (Some(value), None),
+                Change::Changed {
+                    ref old, ref new, ..
+                } => (Some(old), Some(new)),
+            };
+            for (marker, code, value) in [("-", RED, old), ("+", GREEN, new)] {
+                if let Some(value) = value {
+                    let pretty =
+                        serde_json::to_string_pretty(value).expect("a Value always serializes");
+                    for line in pretty.lines() {
+                        report.push_str(&paint(code, &format!("{} {}", marker, line)));
+                        report.push('\n');
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
+
+// panics with the rendered report instead of two walls of Debug output
+#[track_caller]
+pub fn assert_json_eq(expected: &Value, actual: &Value, options: &Options) {
+    let comparison = compare(expected, actual, options);
+    if !comparison.is_equal() {
+        panic!(
+            "JSON values differ ({} change(s)):\n{}",
+            comparison.changes.len(),
+            comparison.render(false)
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($expected:expr, $actual:expr) => {
+        $crate::chapter_3::compare::assert_json_eq(
+            &$expected,
+            &$actual,
+            &$crate::chapter_3::compare::Options::new(),
+        )
+    };
+    ($expected:expr, $actual:expr, $options:expr) => {
+        $crate::chapter_3::compare::assert_json_eq(&$expected, &$actual, &$options)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(comparison: &Comparison) -> Vec<String> {
+        comparison
+            .changes
+            .iter()
+            .map(|c| {
+                let kind = match *c {
+                    Change::Added { .. } => "+",
+                    Change::Removed { .. } => "-",
+                    Change::Changed { .. } => "~",
+                };
+                format!("{}{}", kind, c.path())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_paths() {
+        let expected =
+            json!({"meaningOfLife": 42, "gone": true, "list": [1, 2, 3], "nested": {"a": "b"}});
+        let actual =
+            json!({"meaningOfLife": 43, "new": null, "list": [1, 5], "nested": {"a": "b"}});
+        assert_eq!(
+            paths(&compare(&expected, &actual, &Options::new())),
+            vec!["-/gone", "~/list/1", "-/list/2", "~/meaningOfLife", "+/new"]
+        );
+    }
+
+    #[test]
+    fn equal_values_have_no_changes() {
+        let value = json!({"a": [1, {"b": 2.0}]});
+        assert!(compare(&value, &json!({"a": [1, {"b": 2}]}), &Options::new()).is_equal());
+        assert_json_eq!(value, json!({"a": [1.0, {"b": 2}]}));
+    }
+
+    #[test]
+    fn ignores_array_order_when_asked() {
+        let expected = json!({"tags": ["a", "b", {"c": 1}], "ids": [1, 1, 2]});
+        let actual = json!({"tags": [{"c": 1}, "b", "a"], "ids": [2, 1, 3]});
+        let options = Options::new().ignore_array_order(true);
+        assert_eq!(
+            paths(&compare(&expected, &actual, &options)),
+            vec!["-/ids/1", "+/ids/2"]
+        );
+        assert_eq!(
+            compare(&expected, &actual, &Options::new()).changes.len(),
+            4
+        );
+    }
+
+    #[test]
+    fn ignores_keys_and_paths() {
+        let expected = json!({"meaningOfLife": 42, "updatedAt": 1, "items": [{"updatedAt": 2, "id": 1}], "meta": {"requestId": "x", "v": 1}});
+        let actual = json!({"meaningOfLife": 42, "updatedAt": 9, "items": [{"updatedAt": 8, "id": 1}], "meta": {"requestId": "y", "v": 1}});
+        let options = Options::new()
+            .ignore_key("updatedAt")
+            .ignore_path(Pointer::parse("/meta/requestId").unwrap());
+        assert!(compare(&expected, &actual, &options).is_equal());
+        assert_json_eq!(expected, actual, options.clone().ignore_array_order(true));
+        assert_eq!(
+            paths(&compare(&expected, &actual, &Options::new())).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn renders_a_unified_report() {
+        let comparison = compare(
+            &json!({"meaningOfLife": 42, "list": []}),
+            &json!({"meaningOfLife": "42", "list": [{"a": 1}]}),
+            &Options::new(),
+        );
+        assert_eq!(
+            comparison.to_string(),
+            "--- expected\n+++ actual\n@@ /list/0 @@\n+ {\n+   \"a\": 1\n+ }\n@@ /meaningOfLife @@\n- 42\n+ \"42\"\n"
+        );
+        let colored = comparison.render(true);
+        assert!(colored.contains("\x1b[31m- 42\x1b[0m"));
+        assert!(colored.contains("\x1b[32m+ \"42\"\x1b[0m"));
+        assert!(compare(&json!(1), &json!(2), &Options::new())
+            .to_string()
+            .contains("@@ (root) @@"));
+    }
+
+    #[test]
+    #[should_panic(expected = "@@ /meaningOfLife @@")]
+    fn assertion_panics_with_the_report() {
+        assert_json_eq!(json!({"meaningOfLife": 42}), json!({"meaningOfLife": 43}));
+    }
+}
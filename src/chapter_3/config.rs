@@ -0,0 +1,457 @@
+// layered configuration: defaults, then a file, then environment variables, then command-line
+// overrides, each deep-merged over the last, remembering which layer every final key came from
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde_json::{self, Map, Value};
+
+use chapter_3::formats::{parse_as, Format};
+use chapter_3::printer::minify;
+use chapter_3::{parse_input_to_json_value, type_name, Pointer, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Defaults,
+    File(PathBuf),
+    // the variable's name
+    Env(String),
+    // the override as it was given
+    Cli(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Source::Defaults => write!(f, "defaults"),
+            Source::File(ref path) => write!(f, "file {}", path.display()),
+            Source::Env(ref name) => write!(f, "env {}", name),
+            Source::Cli(ref arg) => write!(f, "cli {}", arg),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Loader {
+    prefix: String,
+    layers: Vec<(Source, Value)>,
+}
+
+impl Default for Loader {
+    fn default() -> Loader {
+        Loader {
+            prefix: "RUSTOLEUM".to_string(),
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl Loader {
+    pub fn new() -> Loader {
+        Loader::default()
+    }
+
+    // environment variables are read as PREFIX__SOME_KEY
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Loader {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn defaults(mut self, defaults: Value) -> Result<Loader> {
+        self.layer(Source::Defaults, defaults)?;
+        Ok(self)
+    }
+
+    // JSON unless the extension says otherwise, eg. config.toml with the toml feature
+    pub fn file<P: AsRef<Path>>(mut self, path: P) -> Result<Loader> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let value = parse_as(&text, Format::from_path(path).unwrap_or(Format::Json))?;
+        self.layer(Source::File(path.to_path_buf()), value)?;
+        Ok(self)
+    }
+
+    // the same, but a file that isn't there is skipped
+    pub fn optional_file<P: AsRef<Path>>(self, path: P) -> Result<Loader> {
+        match fs::metadata(path.as_ref()) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(self),
+            _ => self.file(path),
+        }
+    }
+
+    pub fn env(self) -> Result<Loader> {
+        // env::vars panics on a name or value that isn't UTF-8; skip those instead
+        self.env_vars(env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }))
+    }
+
+    // RUSTOLEUM__MEANING_OF_LIFE=42 sets /meaningOfLife, RUSTOLEUM__AUTHOR__NAME sets /author/name
+    pub fn env_vars<I: IntoIterator<Item = (String, String)>>(mut self, vars: I) -> Result<Loader> {
+        let marker = format!("{}__", self.prefix);
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&marker) && name.len() > marker.len())
+            .collect();
+        // so the outcome doesn't depend on the order the OS lists variables in
+        vars.sort();
+        for (name, raw) in vars {
+            let mut pointer = Pointer::root();
+            for segment in name[marker.len()..].split("__") {
+                pointer.push(camel_case(segment));
+            }
+            self.layer(Source::Env(name), nest(&pointer, typed(&raw)))?;
+        }
+        Ok(self)
+    }
+
+    // "meaningOfLife=42", "author.name=Douglas" or "/author/name=Douglas"
+    pub fn overrides<S: AsRef<str>>(mut self, overrides: &[S]) -> Result<Loader> {
+        for arg in overrides {
+            let arg = arg.as_ref();
+            let (key, raw) = match arg.find('=') {
+                Some(i) => (&arg[..i], &arg[i + 1..]),
+                None => {
+                    return Err(Error::InvalidPointer {
+                        pointer: arg.to_string(),
+                        reason: "an override needs the form key=value",
+                    })
+                }
+            };
+            let pointer = if key.starts_with('/') {
+                Pointer::parse(key)?
+            } else {
+                let mut pointer = Pointer::root();
+                for segment in key.split('.') {
+                    pointer.push(segment);
+                }
+                pointer
+            };
+            self.layer(Source::Cli(arg.to_string()), nest(&pointer, typed(raw)))?;
+        }
+        Ok(self)
+    }
+
+    fn layer(&mut self, source: Source, value: Value) -> Result<()> {
+        if !value.is_object() {
+            return Err(Error::WrongType {
+                path: source.to_string(),
+                expected: "object",
+                found: type_name(&value),
+            });
+        }
+        self.layers.push((source, value));
+        Ok(())
+    }
+
+    pub fn load(&self) -> Config {
+        let mut config = Config {
+            value: Value::Object(Map::new()),
+            provenance: BTreeMap::new(),
+        };
+        for (source, layer) in &self.layers {
+            let mut leaves = Vec::new();
+            collect_leaves(&Pointer::root(), layer, Some(&config.value), &mut leaves);
+            merge(&mut config.value, layer);
+            for leaf in leaves {
+                let key = leaf.to_string();
+                // whatever was under or above this key has been replaced by it
+                config.provenance.retain(|existing, _| {
+                    !(*existing == key
+                        || existing.starts_with(&format!("{}/", key))
+                        || key.starts_with(&format!("{}/", existing)))
+                });
+                config.provenance.insert(key, source.clone());
+            }
+        }
+        config
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub value: Value,
+    // every final leaf, by pointer, with the layer that set it
+    pub provenance: BTreeMap<String, Source>,
+}
+
+impl Config {
+    pub fn source_of(&self, pointer: &str) -> Option<&Source> {
+        self.provenance.get(pointer)
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_value(self.value.clone()).map_err(Error::Deserialize)
+    }
+
+    // one line per key, eg. "/meaningOfLife = 42 (env RUSTOLEUM__MEANING_OF_LIFE)"
+    pub fn explain(&self) -> String {
+        let mut lines = Vec::new();
+        for (key, source) in &self.provenance {
+            let value = Pointer::parse(key)
+                .and_then(|p| p.resolve(&self.value).map(minify))
+                .unwrap_or_default();
+            lines.push(format!("{} = {} ({})", key, value, source));
+        }
+        lines.join("\n")
+    }
+}
+
+// later layers win; objects merge key by key, anything else replaces what was there
+fn merge(base: &mut Value, layer: &Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge(existing, value)
+                    }
+                    _ => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer.clone(),
+    }
+}
+
+// the keys a layer sets once merged over base; arrays count as leaves, since a layer replaces a
+// list rather than merging into it, and so does an empty object unless it lands on an object,
+// which merge leaves alone
+fn collect_leaves(path: &Pointer, value: &Value, base: Option<&Value>, leaves: &mut Vec<Pointer>) {
+    match *value {
+        Value::Object(ref map) if !map.is_empty() => {
+            for (key, child) in map {
+                let under = base.and_then(|b| b.get(key));
+                collect_leaves(&path.child(key.as_str()), child, under, leaves);
+            }
+        }
+        Value::Object(_) if base.is_some_and(Value::is_object) => (),
+        _ if !path.is_root() => leaves.push(path.clone()),
+        _ => (),
+    }
+}
+
+fn nest(pointer: &Pointer, leaf: Value) -> Value {
+    pointer.tokens().iter().rev().fold(leaf, |inner, token| {
+        let mut map = Map::new();
+        map.insert(token.clone(), inner);
+        Value::Object(map)
+    })
+}
+
+// 42, true and [1, 2] keep their JSON types; anything that isn't JSON is a plain string
+fn typed(raw: &str) -> Value {
+    parse_input_to_json_value(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+// MEANING_OF_LIFE -> meaningOfLife
+fn camel_case(segment: &str) -> String {
+    let mut out = String::new();
+    for (i, word) in segment.split('_').filter(|w| !w.is_empty()).enumerate() {
+        let word = word.to_lowercase();
+        if i == 0 {
+            out.push_str(&word);
+        } else {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                out.extend(first.to_uppercase());
+                out.push_str(chars.as_str());
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct AppConfig {
+        meaning_of_life: i64,
+        author: Author,
+        #[serde(default)]
+        tags: Vec<String>,
+        verbose: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Author {
+        name: String,
+        year: u32,
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn defaults() -> Value {
+        json!({"meaningOfLife": 0, "author": {"name": "unknown", "year": 1979}, "tags": ["a"], "verbose": false})
+    }
+
+    #[test]
+    fn merges_every_layer_and_tracks_provenance() {
+        let file = env::temp_dir().join(format!(
+            "rustoleum-config-layers-{}.json",
+            ::std::process::id()
+        ));
+        fs::write(
+            &file,
+            r#"{"author": {"name": "Douglas"}, "tags": ["towel", "fish"]}"#,
+        )
+        .unwrap();
+
+        let config = Loader::new()
+            .defaults(defaults())
+            .unwrap()
+            .file(&file)
+            .unwrap()
+            .env_vars(env(&[
+                ("RUSTOLEUM__MEANING_OF_LIFE", "42"),
+                ("RUSTOLEUM__VERBOSE", "true"),
+                ("OTHER__MEANING_OF_LIFE", "7"),
+                ("PATH", "/usr/bin"),
+            ]))
+            .unwrap()
+            .overrides(&["author.year=1978"])
+            .unwrap()
+            .load();
+
+        assert_eq!(
+            config.value,
+            json!({"meaningOfLife": 42, "author": {"name": "Douglas", "year": 1978}, "tags": ["towel", "fish"], "verbose": true})
+        );
+        assert_eq!(
+            config.source_of("/meaningOfLife"),
+            Some(&Source::Env("RUSTOLEUM__MEANING_OF_LIFE".to_string()))
+        );
+        assert_eq!(
+            config.source_of("/author/name"),
+            Some(&Source::File(file.clone()))
+        );
+        assert_eq!(
+            config.source_of("/author/year"),
+            Some(&Source::Cli("author.year=1978".to_string()))
+        );
+        assert_eq!(config.source_of("/tags"), Some(&Source::File(file.clone())));
+        assert_eq!(config.provenance.len(), 5);
+        fs::remove_file(&file).unwrap();
+
+        let typed: AppConfig = config.deserialize().unwrap();
+        assert_eq!(
+            typed,
+            AppConfig {
+                meaning_of_life: 42,
+                author: Author {
+                    name: "Douglas".to_string(),
+                    year: 1978
+                },
+                tags: vec!["towel".to_string(), "fish".to_string()],
+                verbose: true,
+            }
+        );
+    }
+
+    #[test]
+    fn replacing_a_subtree_drops_its_old_provenance() {
+        let config = Loader::new()
+            .defaults(defaults())
+            .unwrap()
+            .overrides(&["/author=\"anonymous\""])
+            .unwrap()
+            .overrides(&["tags.first=x"])
+            .unwrap()
+            .load();
+        assert_eq!(config.value["author"], json!("anonymous"));
+        assert_eq!(config.source_of("/author/name"), None);
+        assert_eq!(
+            config.source_of("/author"),
+            Some(&Source::Cli("/author=\"anonymous\"".to_string()))
+        );
+        assert_eq!(config.value["tags"], json!({"first": "x"}));
+        assert_eq!(config.source_of("/tags"), None);
+        assert!(config.source_of("/tags/first").is_some());
+        assert!(config
+            .explain()
+            .contains("/author = \"anonymous\" (cli /author=\"anonymous\")"));
+    }
+
+    #[test]
+    fn empty_objects_only_count_where_they_replace_something() {
+        let config = Loader::new()
+            .defaults(defaults())
+            .unwrap()
+            .overrides(&["/author={}", "/meaningOfLife={}", "/extra={}"])
+            .unwrap()
+            .load();
+        assert_eq!(
+            config.value["author"],
+            json!({"name": "unknown", "year": 1979})
+        );
+        assert_eq!(config.source_of("/author"), None);
+        assert_eq!(config.source_of("/author/name"), Some(&Source::Defaults));
+        assert_eq!(config.value["meaningOfLife"], json!({}));
+        assert_eq!(
+            config.source_of("/meaningOfLife"),
+            Some(&Source::Cli("/meaningOfLife={}".to_string()))
+        );
+        assert_eq!(
+            config.source_of("/extra"),
+            Some(&Source::Cli("/extra={}".to_string()))
+        );
+    }
+
+    #[test]
+    fn env_values_keep_their_json_types() {
+        let config = Loader::new()
+            .prefix("APP")
+            .env_vars(env(&[
+                ("APP__MEANING_OF_LIFE", "42"),
+                ("APP__AUTHOR__NAME", "Douglas"),
+                ("APP__CODE", "007"),
+                ("APP__LIST", "[1, 2]"),
+                ("APP__", "ignored"),
+            ]))
+            .unwrap()
+            .load();
+        assert_eq!(
+            config.value,
+            json!({"meaningOfLife": 42, "author": {"name": "Douglas"}, "code": "007", "list": [1, 2]})
+        );
+        assert_eq!(camel_case("MEANING_OF_LIFE"), "meaningOfLife");
+        assert_eq!(camel_case("URL"), "url");
+    }
+
+    #[test]
+    fn reports_bad_layers() {
+        assert!(matches!(
+            Loader::new().overrides(&["meaningOfLife"]),
+            Err(Error::InvalidPointer { .. })
+        ));
+        assert!(matches!(
+            Loader::new().defaults(json!([1])),
+            Err(Error::WrongType { .. })
+        ));
+        assert!(matches!(
+            Loader::new().file("/no/such/config.json"),
+            Err(Error::Io(_))
+        ));
+        assert!(Loader::new().optional_file("/no/such/config.json").is_ok());
+        let wrong: Result<AppConfig> = Loader::new()
+            .defaults(json!({"meaningOfLife": "42"}))
+            .unwrap()
+            .load()
+            .deserialize();
+        assert!(matches!(wrong, Err(Error::Deserialize(_))));
+    }
+}
@@ -0,0 +1,375 @@
+// rustc-style reports for parse errors: the offending line, a caret underline and a hint
+use std::error;
+use std::fmt;
+
+use serde_json::Value;
+
+use chapter_3::parse_input_to_json_value;
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    // 1-based, with the column counted in bytes like serde_json does
+    pub line: usize,
+    pub column: usize,
+    // byte range of the offending text within the original input
+    pub span: (usize, usize),
+    pub hint: Option<String>,
+    input: String,
+}
+
+const RED: &str = "\x1b[31m";
+const BLUE: &str = "\x1b[34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Diagnostic {
+    // only parse errors have a location; anything else gives None
+    pub fn from_error(input: &str, error: &Error) -> Option<Diagnostic> {
+        match *error {
+            Error::Parse(ref e) if e.line() > 0 => Some(Diagnostic::new(
+                input,
+                strip_position(&e.to_string()),
+                e.line(),
+                e.column(),
+            )),
+            Error::Syntax {
+                line,
+                column,
+                ref reason,
+            }
+            | Error::LimitExceeded {
+                line,
+                column,
+                ref reason,
+            } => Some(Diagnostic::new(input, reason.clone(), line, column)),
+            _ => None,
+        }
+    }
+
+    fn new(input: &str, message: String, line: usize, column: usize) -> Diagnostic {
+        let start = offset_of(input, line, column.max(1));
+        let mut diagnostic = Diagnostic {
+            message,
+            line,
+            column: column.max(1),
+            span: (start, start),
+            hint: None,
+            input: input.to_string(),
+        };
+        diagnostic.explain();
+        diagnostic
+    }
+
+    // the offending line, without its newline
+    pub fn source_line(&self) -> &str {
+        let start = self.input[..self.span.0].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.input[start..]
+            .find('\n')
+            .map_or(self.input.len(), |i| start + i);
+        self.input[start..end].trim_end_matches('\r')
+    }
+
+    // widens the span over the offending token and picks a hint for the common mistakes
+    fn explain(&mut self) {
+        let (start, _) = self.span;
+        let rest = &self.input[start..];
+        let first = rest.chars().next();
+        let word_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .unwrap_or(rest.len());
+        let word = &rest[..word_len];
+
+        let (end, hint) = if self.message.starts_with("trailing comma") {
+            // serde points at the closing bracket; the comma is the thing to remove
+            let comma = self.input[..start].rfind(',').unwrap_or(start);
+            self.span.0 = comma;
+            let (line, column) = position_of(&self.input, comma);
+            self.line = line;
+            self.column = column;
+            (comma + 1, Some("remove the trailing comma".to_string()))
+        } else if first == Some('\'') {
+            let close = rest[1..].find('\'').map_or(rest.len(), |i| i + 2);
+            (
+                start + close,
+                Some("single quotes are not valid JSON strings, use double quotes".to_string()),
+            )
+        } else if rest.starts_with("//") || rest.starts_with("/*") {
+            (
+                start + 2,
+                Some("comments are not allowed in JSON".to_string()),
+            )
+        } else if ["NaN", "Infinity", "undefined"].contains(&word) {
+            (
+                start + word_len,
+                Some(format!(
+                    "{} is not a JSON value, use null or a string instead",
+                    word
+                )),
+            )
+        } else if self.message.starts_with("key must be a string") && word_len > 0 {
+            (
+                start + word_len,
+                Some(format!(
+                    "object keys must be double-quoted strings, eg. \"{}\"",
+                    word
+                )),
+            )
+        } else if self.message.starts_with("trailing characters") {
+            (
+                start + rest.len(),
+                Some("only one top-level value is allowed".to_string()),
+            )
+        } else if self.message.starts_with("EOF while parsing") {
+            (
+                start + first.map_or(0, char::len_utf8),
+                Some(
+                    "the input ends early, check for a missing closing bracket or quote"
+                        .to_string(),
+                ),
+            )
+        } else {
+            (start + first.map_or(0, char::len_utf8), None)
+        };
+        // never underline past the end of the offending line
+        let line_end = self.input[self.span.0..]
+            .find('\n')
+            .map_or(self.input.len(), |i| self.span.0 + i);
+        self.span.1 = end.min(line_end).max(self.span.0);
+        self.hint = hint;
+    }
+
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let line_start = self.input[..self.span.0].rfind('\n').map_or(0, |i| i + 1);
+        let gutter = " ".repeat(self.line.to_string().len());
+        // keep tabs so the caret lines up under whatever the terminal does with them
+        let indent: String = self.input[line_start..self.span.0]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = self.input[self.span.0..self.span.1].chars().count().max(1);
+
+        let mut out = String::new();
+        out.push_str(&paint(
+            BOLD,
+            &format!("{}: {}", paint(RED, "error"), self.message),
+        ));
+        out.push('\n');
+        out.push_str(&format!(
+            "{}{} line {}, column {}\n",
+            gutter,
+            paint(BLUE, "-->"),
+            self.line,
+            self.column
+        ));
+        out.push_str(&format!("{} {}\n", gutter, paint(BLUE, "|")));
+        out.push_str(&format!(
+            "{} {} {}\n",
+            paint(BLUE, &self.line.to_string()),
+            paint(BLUE, "|"),
+            self.source_line()
+        ));
+        out.push_str(&format!(
+            "{} {} {}{}\n",
+            gutter,
+            paint(BLUE, "|"),
+            indent,
+            paint(RED, &"^".repeat(width))
+        ));
+        if let Some(ref hint) = self.hint {
+            out.push_str(&format!("{} {}\n", gutter, paint(BLUE, "|")));
+            out.push_str(&format!(
+                "{} {} {}\n",
+                gutter,
+                paint(BLUE, "="),
+                paint(BOLD, &format!("hint: {}", hint))
+            ));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
+
+impl error::Error for Diagnostic {}
+
+// parse_input_to_json_value, but a failure comes back ready to show to a person
+pub fn parse_with_diagnostic(input: &str) -> ::std::result::Result<Value, Diagnostic> {
+    parse_input_to_json_value(input).map_err(|e| {
+        Diagnostic::from_error(input, &e).expect("parsing a str only fails with a located error")
+    })
+}
+
+// serde_json appends " at line N column M", which the report already shows
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(i) => message[..i].to_string(),
+        None => message.to_string(),
+    }
+}
+
+fn offset_of(input: &str, line: usize, column: usize) -> usize {
+    let mut line_start = 0;
+    for _ in 1..line {
+        match input[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => break,
+        }
+    }
+    let mut offset = (line_start + column - 1).min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn position_of(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, offset - line_start + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chapter_3::stream::EventReader;
+
+    fn diagnose(input: &str) -> Diagnostic {
+        parse_with_diagnostic(input).unwrap_err()
+    }
+
+    #[test]
+    fn renders_single_quotes_with_a_hint() {
+        assert_eq!(
+            diagnose("'asdf'").to_string(),
+            "error: expected value\n \
+             --> line 1, column 1\n  \
+             |\n\
+             1 | 'asdf'\n  \
+             | ^^^^^^\n  \
+             |\n  \
+             = hint: single quotes are not valid JSON strings, use double quotes\n"
+        );
+    }
+
+    #[test]
+    fn points_at_the_right_line_and_column() {
+        let d = diagnose("{\n  \"meaningOfLife\": 42,\n  \"é\": x\n}");
+        assert_eq!((d.line, d.column), (3, 9));
+        assert_eq!(d.source_line(), "  \"é\": x");
+        assert_eq!(d.hint, None);
+        assert!(
+            d.to_string().contains("3 |   \"é\": x\n  |        ^\n"),
+            "{}",
+            d
+        );
+    }
+
+    #[test]
+    fn hints_for_common_mistakes() {
+        let cases = vec![
+            (
+                "[1, 2,]",
+                "trailing comma",
+                ",",
+                "remove the trailing comma",
+            ),
+            (
+                "{\"a\": 1,\n}",
+                "trailing comma",
+                ",",
+                "remove the trailing comma",
+            ),
+            (
+                "{meaningOfLife: 42}",
+                "key must be a string",
+                "meaningOfLife",
+                "object keys must be",
+            ),
+            (
+                "// note\n{}",
+                "expected value",
+                "//",
+                "comments are not allowed",
+            ),
+            ("[NaN]", "expected value", "NaN", "NaN is not a JSON value"),
+            (
+                "{} {}",
+                "trailing characters",
+                "{}",
+                "only one top-level value",
+            ),
+            (
+                "[1, 2",
+                "EOF while parsing a list",
+                "2",
+                "the input ends early",
+            ),
+        ];
+        for (input, message, underlined, hint) in cases {
+            let d = diagnose(input);
+            assert_eq!(d.message, message, "{}", input);
+            assert_eq!(&d.input[d.span.0..d.span.1], underlined, "{}", input);
+            assert!(
+                d.hint.as_ref().unwrap().starts_with(hint),
+                "{}: {:?}",
+                input,
+                d.hint
+            );
+        }
+    }
+
+    #[test]
+    fn trailing_commas_point_at_the_comma() {
+        let d = diagnose("[1,\n  2,\n]");
+        assert_eq!((d.line, d.column), (2, 4));
+        assert!(d.to_string().contains("2 |   2,\n  |    ^\n"), "{}", d);
+    }
+
+    #[test]
+    fn handles_empty_input_and_tabs() {
+        let d = diagnose("");
+        assert_eq!(d.span, (0, 0));
+        assert!(d.to_string().contains("1 | \n  | ^\n"));
+        let d = diagnose("[\t'x']");
+        assert!(d.to_string().contains("1 | [\t'x']\n  |  \t^^^\n"), "{}", d);
+    }
+
+    #[test]
+    fn colors_when_asked() {
+        let colored = diagnose("'asdf'").render(true);
+        assert!(colored.contains("\x1b[31m^^^^^^\x1b[0m"));
+        assert!(colored.contains("\x1b[31merror\x1b[0m"));
+        assert!(!diagnose("'asdf'").render(false).contains('\x1b'));
+    }
+
+    #[test]
+    fn works_for_streaming_errors_too() {
+        let input = "[1,\n  'two']";
+        let error = EventReader::new(input.as_bytes())
+            .collect::<::chapter_3::Result<Vec<_>>>()
+            .unwrap_err();
+        let d = Diagnostic::from_error(input, &error).unwrap();
+        assert_eq!((d.line, d.column), (2, 3));
+        assert_eq!(&d.input[d.span.0..d.span.1], "'two'");
+        assert!(Diagnostic::from_error(
+            input,
+            &Error::MissingKey {
+                path: "/a".to_string()
+            }
+        )
+        .is_none());
+    }
+}
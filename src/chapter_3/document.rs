@@ -0,0 +1,256 @@
+// typed documents: serde derive does the field work, and an UnknownFields policy decides
+// what happens to keys the struct doesn't know about
+use serde::de::DeserializeOwned;
+use serde_json::{self, Map, Value};
+
+use chapter_3::{parse_input_to_json_value, type_name, Result};
+use error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownFields {
+    // fail, naming every key that wasn't expected
+    Deny,
+    // keep them in the document's catch-all map
+    Collect,
+    // drop them
+    Ignore,
+}
+
+// implemented by every typed document; the catch-all map is a `#[serde(flatten)]` field
+pub trait Document: DeserializeOwned {
+    fn unknown_fields(&mut self) -> &mut Map<String, Value>;
+}
+
+pub fn from_value<T: Document>(value: Value, policy: UnknownFields) -> Result<T> {
+    let mut document: T = serde_json::from_value(value).map_err(Error::Deserialize)?;
+    match policy {
+        UnknownFields::Collect => (),
+        UnknownFields::Ignore => document.unknown_fields().clear(),
+        UnknownFields::Deny => {
+            let fields: Vec<String> = document.unknown_fields().keys().cloned().collect();
+            if !fields.is_empty() {
+                return Err(Error::UnknownFields { fields });
+            }
+        }
+    }
+    Ok(document)
+}
+
+pub fn from_str<T: Document>(input: &str, policy: UnknownFields) -> Result<T> {
+    from_value(parse_input_to_json_value(input)?, policy)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeaningDocument {
+    #[serde(rename = "meaningOfLife")]
+    pub meaning_of_life: i64,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Document for MeaningDocument {
+    fn unknown_fields(&mut self) -> &mut Map<String, Value> {
+        &mut self.extra
+    }
+}
+
+// the first shape we shipped, before author and tags existed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeaningDocumentV1 {
+    #[serde(rename = "meaningOfLife")]
+    pub meaning_of_life: i64,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Document for MeaningDocumentV1 {
+    fn unknown_fields(&mut self) -> &mut Map<String, Value> {
+        &mut self.extra
+    }
+}
+
+// picked by the document's "version" key; documents without one are version 1
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionedMeaning {
+    V1(MeaningDocumentV1),
+    V2(MeaningDocument),
+}
+
+pub const CURRENT_VERSION: u64 = 2;
+
+impl VersionedMeaning {
+    pub fn from_value(mut value: Value, policy: UnknownFields) -> Result<VersionedMeaning> {
+        let version = match value.as_object_mut().and_then(|map| map.remove("version")) {
+            None => 1,
+            Some(other) => match other.as_u64() {
+                Some(version) => version,
+                None => {
+                    return Err(Error::WrongType {
+                        path: "/version".to_string(),
+                        expected: "u64",
+                        found: type_name(&other),
+                    })
+                }
+            },
+        };
+        match version {
+            1 => from_value(value, policy).map(VersionedMeaning::V1),
+            2 => from_value(value, policy).map(VersionedMeaning::V2),
+            other => Err(Error::UnsupportedVersion { version: other }),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        match *self {
+            VersionedMeaning::V1(_) => 1,
+            VersionedMeaning::V2(_) => 2,
+        }
+    }
+
+    // upgrades older documents, filling in what they didn't have with defaults
+    pub fn into_latest(self) -> MeaningDocument {
+        match self {
+            VersionedMeaning::V1(v1) => MeaningDocument {
+                meaning_of_life: v1.meaning_of_life,
+                author: None,
+                tags: Vec::new(),
+                extra: v1.extra,
+            },
+            VersionedMeaning::V2(v2) => v2,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut value = match *self {
+            VersionedMeaning::V1(ref v1) => serde_json::to_value(v1),
+            VersionedMeaning::V2(ref v2) => serde_json::to_value(v2),
+        }
+        .expect("documents always serialize");
+        if let Some(map) = value.as_object_mut() {
+            map.insert("version".to_string(), Value::from(self.version()));
+        }
+        value
+    }
+}
+
+pub fn parse_meaning_document(input: &str, policy: UnknownFields) -> Result<MeaningDocument> {
+    VersionedMeaning::from_value(parse_input_to_json_value(input)?, policy)
+        .map(VersionedMeaning::into_latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_with_renames_and_defaults() {
+        let doc: MeaningDocument =
+            from_str(r#"{"meaningOfLife": 42}"#, UnknownFields::Deny).unwrap();
+        assert_eq!(
+            doc,
+            MeaningDocument {
+                meaning_of_life: 42,
+                author: None,
+                tags: vec![],
+                extra: Map::new(),
+            }
+        );
+        let doc: MeaningDocument = from_str(
+            r#"{"meaningOfLife": 42, "author": "Douglas", "tags": ["towel"]}"#,
+            UnknownFields::Deny,
+        )
+        .unwrap();
+        assert_eq!(doc.author, Some("Douglas".to_string()));
+        assert_eq!(doc.tags, vec!["towel".to_string()]);
+    }
+
+    #[test]
+    fn applies_the_unknown_field_policy() {
+        let input = r#"{"meaningOfLife": 42, "mood": "calm", "towel": true}"#;
+        match from_str::<MeaningDocument>(input, UnknownFields::Deny) {
+            Err(Error::UnknownFields { fields }) => assert_eq!(fields, vec!["mood", "towel"]),
+            other => panic!("unexpected {:?}", other),
+        }
+        let collected: MeaningDocument = from_str(input, UnknownFields::Collect).unwrap();
+        assert_eq!(
+            Value::Object(collected.extra),
+            json!({"mood": "calm", "towel": true})
+        );
+        let ignored: MeaningDocument = from_str(input, UnknownFields::Ignore).unwrap();
+        assert!(ignored.extra.is_empty());
+    }
+
+    #[test]
+    fn reports_shape_errors() {
+        assert!(matches!(
+            from_str::<MeaningDocument>(r#"{"author": "Douglas"}"#, UnknownFields::Ignore),
+            Err(Error::Deserialize(_))
+        ));
+        assert!(matches!(
+            from_str::<MeaningDocument>(r#"{"meaningOfLife": "42"}"#, UnknownFields::Ignore),
+            Err(Error::Deserialize(_))
+        ));
+        assert!(matches!(
+            from_str::<MeaningDocument>("'asdf'", UnknownFields::Ignore),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn picks_the_version_and_upgrades() {
+        let v1 = parse_meaning_document(r#"{"meaningOfLife": 42}"#, UnknownFields::Deny).unwrap();
+        assert_eq!(v1.meaning_of_life, 42);
+        assert_eq!(v1.author, None);
+
+        // author only exists from version 2, so a v1 document carrying it is unknown
+        let input = r#"{"version": 1, "meaningOfLife": 42, "author": "Douglas"}"#;
+        assert!(matches!(
+            parse_meaning_document(input, UnknownFields::Deny),
+            Err(Error::UnknownFields { .. })
+        ));
+        let v2 = parse_meaning_document(
+            &input.replace("\"version\": 1", "\"version\": 2"),
+            UnknownFields::Deny,
+        )
+        .unwrap();
+        assert_eq!(v2.author, Some("Douglas".to_string()));
+
+        assert!(matches!(
+            parse_meaning_document(
+                r#"{"version": 3, "meaningOfLife": 42}"#,
+                UnknownFields::Deny
+            ),
+            Err(Error::UnsupportedVersion { version: 3 })
+        ));
+        assert!(matches!(
+            parse_meaning_document(
+                r#"{"version": "2", "meaningOfLife": 42}"#,
+                UnknownFields::Deny
+            ),
+            Err(Error::WrongType { .. })
+        ));
+    }
+
+    #[test]
+    fn serializes_with_the_version_tag() {
+        let doc = VersionedMeaning::from_value(
+            json!({"version": 2, "meaningOfLife": 42, "tags": ["towel"], "mood": "calm"}),
+            UnknownFields::Collect,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.to_value(),
+            json!({"version": 2, "meaningOfLife": 42, "author": null, "tags": ["towel"], "mood": "calm"})
+        );
+        let round_trip = VersionedMeaning::from_value(doc.to_value(), UnknownFields::Deny);
+        assert!(round_trip.is_err());
+        assert_eq!(
+            VersionedMeaning::from_value(doc.to_value(), UnknownFields::Collect).unwrap(),
+            doc
+        );
+    }
+}
@@ -0,0 +1,434 @@
+// numbers exactly as written: read straight from the streaming reader's raw text, so values
+// like 1e400 or 18446744073709551616 survive until the caller picks a type, and a type that
+// can't hold them is an error instead of a None
+//
+// only single numbers looked up by pointer with number_at are read this way; the whole-document
+// parser and every other reader still go through serde_json's Number
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use chapter_3::stream::{event_type, is_json_number, seek, Event, EventReader};
+use chapter_3::{Pointer, Result};
+use error::Error;
+
+#[derive(Debug, Clone)]
+pub struct ExactNumber {
+    text: String,
+    negative: bool,
+    // significant digits without leading or trailing zeros; empty for zero
+    digits: String,
+    // the value is digits * 10^exponent
+    exponent: i64,
+    // places after the point as written, eg. 2 for both 1.50 and 150e-2; negative for 15e1
+    places: i64,
+}
+
+impl ExactNumber {
+    pub fn parse(text: &str) -> Result<ExactNumber> {
+        if !is_json_number(text) {
+            return Err(Error::Number {
+                number: text.to_string(),
+                target: "number",
+                reason: "is not a JSON number",
+            });
+        }
+        let negative = text.starts_with('-');
+        let unsigned = text.trim_start_matches('-');
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(i) => (&unsigned[..i], &unsigned[i + 1..]),
+            None => (unsigned, "0"),
+        };
+        let (int, frac) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+            None => (mantissa, ""),
+        };
+        let out_of_range = || Error::Number {
+            number: text.to_string(),
+            target: "number",
+            reason: "has an exponent out of range",
+        };
+        let written = exponent
+            .trim_start_matches('+')
+            .parse::<i64>()
+            .map_err(|_| out_of_range())?;
+
+        let all = format!("{}{}", int, frac);
+        let significant = all.trim_start_matches('0');
+        let trimmed = significant.trim_end_matches('0');
+        let exponent = written
+            .checked_sub(frac.len() as i64)
+            .and_then(|e| e.checked_add((significant.len() - trimmed.len()) as i64))
+            .ok_or_else(out_of_range)?;
+        Ok(ExactNumber {
+            text: text.to_string(),
+            negative: negative && !trimmed.is_empty(),
+            digits: trimmed.to_string(),
+            exponent: if trimmed.is_empty() { 0 } else { exponent },
+            places: (frac.len() as i64).saturating_sub(written),
+        })
+    }
+
+    // the number as it appeared in the input
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // the significant digits, without leading or trailing zeros and empty for zero; with
+    // exponent and is_negative they give the value exactly, as digits * 10^exponent
+    pub fn digits(&self) -> &str {
+        &self.digits
+    }
+
+    pub fn exponent(&self) -> i64 {
+        self.exponent
+    }
+
+    // places after the point as written, eg. 2 for both 1.50 and 150e-2; negative for 15e1
+    pub fn places(&self) -> i64 {
+        self.places
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.exponent >= 0
+    }
+
+    // the same value with a canonical spelling, eg. "1.50e2" and "150" both give "15e1"
+    pub fn normalized(&self) -> String {
+        if self.is_zero() {
+            return "0".to_string();
+        }
+        format!(
+            "{}{}e{}",
+            if self.negative { "-" } else { "" },
+            self.digits,
+            self.exponent
+        )
+    }
+
+    // where the leading digit sits; 1 for 1-9, 2 for 10-99, 0 for 0.1-0.9
+    fn magnitude(&self) -> i128 {
+        i128::from(self.exponent) + self.digits.len() as i128
+    }
+
+    fn error(&self, target: &'static str, reason: &'static str) -> Error {
+        Error::Number {
+            number: self.text.clone(),
+            target,
+            reason,
+        }
+    }
+
+    // the integer's digits, once it's known to be an integer that fits in `max_digits`
+    fn integer_digits(&self, target: &'static str, max_digits: i128) -> Result<String> {
+        if !self.is_integer() {
+            return Err(self.error(target, "has a fractional part"));
+        }
+        if self.magnitude() > max_digits {
+            return Err(self.error(target, "is out of range"));
+        }
+        Ok(format!(
+            "{}{}{}",
+            if self.negative { "-" } else { "" },
+            self.digits,
+            "0".repeat(self.exponent as usize)
+        ))
+    }
+
+    pub fn to_i128(&self) -> Result<i128> {
+        if self.is_zero() {
+            return Ok(0);
+        }
+        self.integer_digits("i128", 39)?
+            .parse()
+            .map_err(|_| self.error("i128", "is out of range"))
+    }
+
+    pub fn to_u128(&self) -> Result<u128> {
+        if self.is_zero() {
+            return Ok(0);
+        }
+        if self.negative {
+            return Err(self.error("u128", "is negative"));
+        }
+        self.integer_digits("u128", 39)?
+            .parse()
+            .map_err(|_| self.error("u128", "is out of range"))
+    }
+
+    pub fn to_i64(&self) -> Result<i64> {
+        let wide = self.to_i128().map_err(|_| self.narrow_error("i64"))?;
+        i64::try_from(wide).map_err(|_| self.error("i64", "is out of range"))
+    }
+
+    pub fn to_u64(&self) -> Result<u64> {
+        let wide = self.to_u128().map_err(|_| self.narrow_error("u64"))?;
+        u64::try_from(wide).map_err(|_| self.error("u64", "is out of range"))
+    }
+
+    // the same reason the wide conversion gave, but naming the type that was asked for
+    fn narrow_error(&self, target: &'static str) -> Error {
+        if !self.is_integer() {
+            self.error(target, "has a fractional part")
+        } else if self.negative && target.starts_with('u') {
+            self.error(target, "is negative")
+        } else {
+            self.error(target, "is out of range")
+        }
+    }
+
+    // the nearest f64; only numbers past f64's range are an error
+    pub fn to_f64(&self) -> Result<f64> {
+        match self.text.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(f),
+            _ => Err(self.error("f64", "is out of range")),
+        }
+    }
+}
+
+impl FromStr for ExactNumber {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ExactNumber> {
+        ExactNumber::parse(s)
+    }
+}
+
+impl fmt::Display for ExactNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// compared by value, so 1.0, 1 and 10e-1 are all equal
+impl Ord for ExactNumber {
+    fn cmp(&self, other: &ExactNumber) -> Ordering {
+        let sign = |n: &ExactNumber| {
+            if n.is_zero() {
+                0
+            } else if n.negative {
+                -1
+            } else {
+                1
+            }
+        };
+        let by_sign = sign(self).cmp(&sign(other));
+        if by_sign != Ordering::Equal || self.is_zero() {
+            return by_sign;
+        }
+        // trailing zeros are trimmed, so comparing the digit strings compares the values
+        let magnitude = self
+            .magnitude()
+            .cmp(&other.magnitude())
+            .then_with(|| self.digits.cmp(&other.digits));
+        if self.negative {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl PartialOrd for ExactNumber {
+    fn partial_cmp(&self, other: &ExactNumber) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ExactNumber {
+    fn eq(&self, other: &ExactNumber) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ExactNumber {}
+
+// the number at the pointer, straight from the input's text
+pub fn number_at<R: Read>(input: R, pointer: &str) -> Result<ExactNumber> {
+    let pointer = Pointer::parse(pointer)?;
+    let mut events = EventReader::new(input);
+    match seek(&mut events, &pointer)? {
+        Some(Event::Number(raw)) => ExactNumber::parse(&raw),
+        Some(other) => Err(Error::WrongType {
+            path: pointer.to_string(),
+            expected: "number",
+            found: event_type(&other),
+        }),
+        None => Err(Error::MissingKey {
+            path: pointer.to_string(),
+        }),
+    }
+}
+
+pub fn get_meaning_of_life_exact(input: &str) -> Result<ExactNumber> {
+    number_at(input.as_bytes(), "/meaningOfLife")
+}
+
+// get_meaning_of_life for answers too big for an i64
+pub fn get_meaning_of_life_i128(input: &str) -> Result<i128> {
+    get_meaning_of_life_exact(input)?.to_i128()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n(text: &str) -> ExactNumber {
+        ExactNumber::parse(text).unwrap()
+    }
+
+    fn reason<T: fmt::Debug>(result: Result<T>) -> &'static str {
+        match result {
+            Err(Error::Number { reason, .. }) => reason,
+            other => panic!("expected a number error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_big_numbers_exactly() {
+        let input = r#"{"meaningOfLife": 18446744073709551616, "tiny": 1e-400, "huge": 1e400}"#;
+        let answer = get_meaning_of_life_exact(input).unwrap();
+        assert_eq!(answer.as_str(), "18446744073709551616");
+        assert_eq!(answer.to_u128().unwrap(), 18446744073709551616);
+        assert_eq!(
+            get_meaning_of_life_i128(input).unwrap(),
+            18446744073709551616
+        );
+        assert_eq!(reason(answer.to_u64()), "is out of range");
+        assert_eq!(
+            number_at(input.as_bytes(), "/huge").unwrap().normalized(),
+            "1e400"
+        );
+        assert_eq!(
+            number_at(input.as_bytes(), "/tiny").unwrap().to_string(),
+            "1e-400"
+        );
+        // the whole-document parser can't even get this far
+        assert!(
+            ::chapter_3::get_meaning_of_life(&input.replace("18446744073709551616", "1e400"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn converts_to_integers_or_says_why_not() {
+        assert_eq!(n("42").to_i64().unwrap(), 42);
+        assert_eq!(n("4.2e1").to_i64().unwrap(), 42);
+        assert_eq!(n("4200e-2").to_i128().unwrap(), 42);
+        assert_eq!(n("-0").to_u128().unwrap(), 0);
+        assert_eq!(n("0e999999").to_i64().unwrap(), 0);
+        assert_eq!(
+            n("-170141183460469231731687303715884105728")
+                .to_i128()
+                .unwrap(),
+            i128::MIN
+        );
+        assert_eq!(
+            n("340282366920938463463374607431768211455")
+                .to_u128()
+                .unwrap(),
+            u128::MAX
+        );
+        assert_eq!(
+            reason(n("170141183460469231731687303715884105728").to_i128()),
+            "is out of range"
+        );
+        assert_eq!(
+            reason(n("340282366920938463463374607431768211456").to_u128()),
+            "is out of range"
+        );
+        assert_eq!(reason(n("1e400").to_i128()), "is out of range");
+        assert_eq!(reason(n("42.5").to_i64()), "has a fractional part");
+        assert_eq!(reason(n("1e-1").to_u64()), "has a fractional part");
+        assert_eq!(reason(n("-1").to_u128()), "is negative");
+        assert_eq!(reason(n("-1").to_u64()), "is negative");
+        assert_eq!(reason(n("9223372036854775808").to_i64()), "is out of range");
+    }
+
+    #[test]
+    fn converts_to_floats() {
+        assert_eq!(n("0.1").to_f64().unwrap(), 0.1);
+        assert_eq!(n("1e-400").to_f64().unwrap(), 0.0);
+        assert_eq!(reason(n("-1e400").to_f64()), "is out of range");
+    }
+
+    #[test]
+    fn exposes_the_exact_parts() {
+        let parts = |text: &str| {
+            let number = n(text);
+            (
+                number.digits().to_string(),
+                number.exponent(),
+                number.places(),
+            )
+        };
+        assert_eq!(parts("1.50"), ("15".to_string(), -1, 2));
+        assert_eq!(parts("150e-2"), ("15".to_string(), -1, 2));
+        assert_eq!(parts("15e1"), ("15".to_string(), 1, -1));
+        assert_eq!(parts("-0.0"), ("".to_string(), 0, 1));
+        assert_eq!(
+            parts("18446744073709551616e-400"),
+            ("18446744073709551616".to_string(), -400, 400)
+        );
+        assert!(n("-1.5").is_negative());
+    }
+
+    #[test]
+    fn compares_by_value() {
+        assert_eq!(n("1.0"), n("1"));
+        assert_eq!(n("10e-1"), n("0.1e1"));
+        assert_eq!(n("-0.0"), n("0"));
+        assert_eq!(n("150").normalized(), n("1.50e2").normalized());
+        let mut sorted = [
+            n("1e400"),
+            n("-2"),
+            n("0.5"),
+            n("-1e400"),
+            n("0"),
+            n("0.05"),
+            n("18446744073709551616"),
+        ];
+        sorted.sort();
+        let texts: Vec<&str> = sorted.iter().map(ExactNumber::as_str).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "-1e400",
+                "-2",
+                "0",
+                "0.05",
+                "0.5",
+                "18446744073709551616",
+                "1e400"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cant_read() {
+        assert_eq!(reason(ExactNumber::parse("1.")), "is not a JSON number");
+        assert_eq!(reason(ExactNumber::parse("NaN")), "is not a JSON number");
+        assert_eq!(
+            reason(ExactNumber::parse("1e99999999999999999999")),
+            "has an exponent out of range"
+        );
+        match number_at(r#"{"meaningOfLife": "42"}"#.as_bytes(), "/meaningOfLife") {
+            Err(Error::WrongType { found, .. }) => assert_eq!(found, "string"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            get_meaning_of_life_exact("{}"),
+            Err(Error::MissingKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,409 @@
+// YAML, TOML and JSON5 normalized into the same Value the JSON parser produces;
+// each format beyond JSON sits behind a cargo feature of the same name
+use std::path::Path;
+
+use serde_json::Value;
+
+use chapter_3::{extract_from, parse_input_to_json_value, FromJson, Result};
+use error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Json5,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Json5 => "json5",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "json5" => Some(Format::Json5),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Format::from_extension)
+    }
+
+    // a best guess from the content alone; strict JSON wins whenever it parses
+    pub fn detect(input: &str) -> Format {
+        if parse_input_to_json_value(input).is_ok() {
+            return Format::Json;
+        }
+        let trimmed = input.trim_start();
+        let lines = || {
+            input
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        };
+        if lines().any(|l| is_toml_table(l) || is_toml_assignment(l)) {
+            Format::Toml
+        } else if trimmed.starts_with('{')
+            || trimmed.starts_with('[')
+            || trimmed.starts_with("//")
+            || trimmed.starts_with("/*")
+        {
+            Format::Json5
+        } else if trimmed.starts_with("---")
+            || lines().any(|l| l.starts_with("- ") || l.contains(": ") || l.ends_with(':'))
+        {
+            Format::Yaml
+        } else {
+            Format::Json
+        }
+    }
+}
+
+fn is_toml_table(line: &str) -> bool {
+    line.starts_with('[')
+        && line.ends_with(']')
+        && line
+            .trim_matches(|c| c == '[' || c == ']')
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-.\" ".contains(c))
+}
+
+fn is_toml_assignment(line: &str) -> bool {
+    match line.find('=') {
+        Some(i) => {
+            let key = line[..i].trim();
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "_-.\"".contains(c))
+        }
+        None => false,
+    }
+}
+
+pub fn parse_as(input: &str, format: Format) -> Result<Value> {
+    match format {
+        Format::Json => parse_input_to_json_value(input),
+        Format::Json5 => parse_json5(input),
+        Format::Yaml => parse_yaml(input),
+        Format::Toml => parse_toml(input),
+    }
+}
+
+pub fn parse_any(input: &str) -> Result<Value> {
+    parse_as(input, Format::detect(input))
+}
+
+// get_meaning_of_life, but for whichever format the input turns out to be
+pub fn extract<T: FromJson>(input: &str, pointer: &str) -> Result<T> {
+    extract_from(&parse_any(input)?, pointer)
+}
+
+#[cfg(any(feature = "yaml", feature = "toml", feature = "json5"))]
+fn format_error<E: ::std::fmt::Display>(format: Format) -> impl Fn(E) -> Error {
+    move |e| Error::Format {
+        format: format.name(),
+        reason: e.to_string(),
+    }
+}
+
+#[cfg(feature = "json5")]
+fn parse_json5(input: &str) -> Result<Value> {
+    ::json5::from_str(input)
+        .map(|Finite(value)| value)
+        .map_err(format_error(Format::Json5))
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(input: &str) -> Result<Value> {
+    ::serde_yaml::from_str(input)
+        .map(|Finite(value)| value)
+        .map_err(format_error(Format::Yaml))
+}
+
+// a Value read through serde the way serde_json's own Deserialize does it, except NaN and the
+// infinities (YAML's .nan and .inf, JSON5's NaN and Infinity) are an error instead of a null
+#[cfg(any(feature = "yaml", feature = "json5"))]
+struct Finite(Value);
+
+#[cfg(any(feature = "yaml", feature = "json5"))]
+impl<'de> ::serde::Deserialize<'de> for Finite {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Finite, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(FiniteVisitor).map(Finite)
+    }
+}
+
+#[cfg(any(feature = "yaml", feature = "json5"))]
+struct FiniteVisitor;
+
+#[cfg(any(feature = "yaml", feature = "json5"))]
+impl<'de> ::serde::de::Visitor<'de> for FiniteVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("any value with a JSON equivalent")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> ::std::result::Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> ::std::result::Result<Value, E> {
+        Ok(Value::from(i))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> ::std::result::Result<Value, E> {
+        Ok(Value::from(u))
+    }
+
+    fn visit_f64<E: ::serde::de::Error>(self, f: f64) -> ::std::result::Result<Value, E> {
+        ::serde_json::Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or_else(|| E::custom(format!("{} has no JSON equivalent", f)))
+    }
+
+    fn visit_str<E>(self, s: &str) -> ::std::result::Result<Value, E> {
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> ::std::result::Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_unit<E>(self) -> ::std::result::Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> ::std::result::Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> ::std::result::Result<Value, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<Value, A::Error>
+    where
+        A: ::serde::de::SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(Finite(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> ::std::result::Result<Value, A::Error>
+    where
+        A: ::serde::de::MapAccess<'de>,
+    {
+        let mut object = ::serde_json::Map::new();
+        while let Some((key, Finite(value))) = map.next_entry::<String, Finite>()? {
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(input: &str) -> Result<Value> {
+    let table: ::toml::Table = input.parse().map_err(format_error(Format::Toml))?;
+    toml_to_json(::toml::Value::Table(table))
+}
+
+// done by hand rather than through serde so datetimes come out as plain strings
+#[cfg(feature = "toml")]
+fn toml_to_json(value: ::toml::Value) -> Result<Value> {
+    use toml::Value as Toml;
+
+    Ok(match value {
+        Toml::String(s) => Value::String(s),
+        Toml::Integer(i) => Value::from(i),
+        Toml::Float(f) => ::serde_json::Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or_else(|| Error::Format {
+                format: "toml",
+                reason: format!("{} has no JSON equivalent", f),
+            })?,
+        Toml::Boolean(b) => Value::Bool(b),
+        Toml::Datetime(d) => Value::String(d.to_string()),
+        Toml::Array(items) => {
+            Value::Array(items.into_iter().map(toml_to_json).collect::<Result<_>>()?)
+        }
+        Toml::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| toml_to_json(v).map(|v| (k, v)))
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
+
+#[cfg(not(feature = "json5"))]
+fn parse_json5(_input: &str) -> Result<Value> {
+    Err(Error::UnsupportedFormat { format: "json5" })
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_input: &str) -> Result<Value> {
+    Err(Error::UnsupportedFormat { format: "yaml" })
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_input: &str) -> Result<Value> {
+    Err(Error::UnsupportedFormat { format: "toml" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "---\n# the answer\nmeaningOfLife: 42\nauthor:\n  name: Douglas\ntags:\n  - towel\n  - fish\n";
+    const TOML: &str =
+        "meaningOfLife = 42\ntags = [\"towel\", \"fish\"]\n\n[author]\nname = \"Douglas\"\n";
+    const JSON5: &str = "{\n  // the answer\n  meaningOfLife: 42,\n  author: {name: 'Douglas',},\n  tags: ['towel', 'fish',],\n}";
+
+    #[cfg(any(feature = "yaml", feature = "toml", feature = "json5"))]
+    fn expected() -> Value {
+        json!({"meaningOfLife": 42, "author": {"name": "Douglas"}, "tags": ["towel", "fish"]})
+    }
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(Format::detect(r#"{"meaningOfLife": 42}"#), Format::Json);
+        assert_eq!(Format::detect("42"), Format::Json);
+        assert_eq!(Format::detect(YAML), Format::Yaml);
+        assert_eq!(Format::detect(TOML), Format::Toml);
+        assert_eq!(Format::detect(JSON5), Format::Json5);
+        assert_eq!(Format::detect("- a\n- b\n"), Format::Yaml);
+        assert_eq!(Format::detect("[server]\nport = 80\n"), Format::Toml);
+        assert_eq!(Format::from_extension("YML"), Some(Format::Yaml));
+        assert_eq!(
+            Format::from_path(Path::new("config.toml")),
+            Some(Format::Toml)
+        );
+        assert_eq!(Format::from_path(Path::new("README")), None);
+    }
+
+    #[test]
+    fn json_still_rejects_single_quotes() {
+        assert_eq!(Format::detect("'asdf'"), Format::Json);
+        assert!(parse_any("'asdf'").is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parses_yaml() {
+        assert_eq!(parse_as(YAML, Format::Yaml).unwrap(), expected());
+        assert_eq!(extract::<i64>(YAML, "/meaningOfLife").unwrap(), 42);
+        assert!(matches!(
+            parse_as("a: [1", Format::Yaml),
+            Err(Error::Format { format: "yaml", .. })
+        ));
+        assert_eq!(
+            parse_as("a: [1.5, ~, {b: -3}]", Format::Yaml).unwrap(),
+            json!({"a": [1.5, null, {"b": -3}]})
+        );
+        for non_finite in ["a: .nan", "a: .inf", "a: [-.inf]"] {
+            assert!(
+                matches!(
+                    parse_as(non_finite, Format::Yaml),
+                    Err(Error::Format { format: "yaml", .. })
+                ),
+                "{}",
+                non_finite
+            );
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn parses_toml() {
+        assert_eq!(parse_as(TOML, Format::Toml).unwrap(), expected());
+        assert_eq!(extract::<i64>(TOML, "/meaningOfLife").unwrap(), 42);
+        assert_eq!(
+            parse_as("when = 1979-10-12T00:00:00Z", Format::Toml).unwrap(),
+            json!({"when": "1979-10-12T00:00:00Z"})
+        );
+        assert!(matches!(
+            parse_as("nan = nan", Format::Toml),
+            Err(Error::Format { format: "toml", .. })
+        ));
+        assert!(matches!(
+            parse_as("a = ", Format::Toml),
+            Err(Error::Format { format: "toml", .. })
+        ));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn parses_json5() {
+        assert_eq!(parse_as(JSON5, Format::Json5).unwrap(), expected());
+        assert_eq!(extract::<i64>(JSON5, "/meaningOfLife").unwrap(), 42);
+        assert!(matches!(
+            parse_as("{a: }", Format::Json5),
+            Err(Error::Format {
+                format: "json5",
+                ..
+            })
+        ));
+        for non_finite in ["{a: NaN}", "{a: Infinity}", "[-Infinity]"] {
+            assert!(
+                matches!(
+                    parse_as(non_finite, Format::Json5),
+                    Err(Error::Format {
+                        format: "json5",
+                        ..
+                    })
+                ),
+                "{}",
+                non_finite
+            );
+        }
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    #[test]
+    fn yaml_needs_its_feature() {
+        assert!(matches!(
+            parse_any(YAML),
+            Err(Error::UnsupportedFormat { format: "yaml" })
+        ));
+    }
+
+    #[cfg(not(feature = "toml"))]
+    #[test]
+    fn toml_needs_its_feature() {
+        assert!(matches!(
+            parse_any(TOML),
+            Err(Error::UnsupportedFormat { format: "toml" })
+        ));
+    }
+
+    #[cfg(not(feature = "json5"))]
+    #[test]
+    fn json5_needs_its_feature() {
+        assert!(matches!(
+            parse_any(JSON5),
+            Err(Error::UnsupportedFormat { format: "json5" })
+        ));
+    }
+}
@@ -0,0 +1,975 @@
+// JSONPath queries, a subset of RFC 9535, eg. "$.users[?(@.age > 30)].meaningOfLife"; the
+// function extensions (length, count, match, search and value) aren't supported
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{self, Value};
+
+use chapter_3::{compare_numbers, json_eq, parse_input_to_json_value, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    query: Query,
+}
+
+// one node picked out by a query, along with its normalized path, eg. "$['users'][0]"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    pub path: String,
+    pub value: &'a Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Operand),
+    Compare(Operand, CompareOp, Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Literal(Value),
+    // relative to the current node (@) or the document root ($)
+    Current(Query),
+    Root(Query),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath> {
+        let mut parser = Parser { query, pos: 0 };
+        parser.expect('$')?;
+        let segments = parser.segments()?;
+        parser.skip_whitespace();
+        if parser.pos < query.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        Ok(JsonPath {
+            query: Query { segments },
+        })
+    }
+
+    pub fn find<'a>(&self, root: &'a Value) -> Vec<Match<'a>> {
+        self.query
+            .evaluate(root, root)
+            .into_iter()
+            .map(|(path, value)| Match {
+                path: normalized_path(&path),
+                value,
+            })
+            .collect()
+    }
+
+    pub fn find_values<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        self.query
+            .evaluate(root, root)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<JsonPath> {
+        JsonPath::parse(s)
+    }
+}
+
+pub fn query<'a>(json: &'a Value, path: &str) -> Result<Vec<Match<'a>>> {
+    Ok(JsonPath::parse(path)?.find(json))
+}
+
+// parses the input and returns owned copies of every match, paired with its normalized path
+pub fn query_input(input: &str, path: &str) -> Result<Vec<(String, Value)>> {
+    let path = JsonPath::parse(path)?;
+    let json = parse_input_to_json_value(input)?;
+    Ok(path
+        .find(&json)
+        .into_iter()
+        .map(|m| (m.path, m.value.clone()))
+        .collect())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+type Node<'a> = (Vec<Step>, &'a Value);
+
+fn normalized_path(steps: &[Step]) -> String {
+    let mut path = "$".to_string();
+    for step in steps {
+        match *step {
+            Step::Key(ref key) => {
+                path.push_str("['");
+                for c in key.chars() {
+                    match c {
+                        '\'' => path.push_str("\\'"),
+                        '\\' => path.push_str("\\\\"),
+                        '\u{8}' => path.push_str("\\b"),
+                        '\u{c}' => path.push_str("\\f"),
+                        '\n' => path.push_str("\\n"),
+                        '\r' => path.push_str("\\r"),
+                        '\t' => path.push_str("\\t"),
+                        c if c < ' ' => path.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => path.push(c),
+                    }
+                }
+                path.push_str("']");
+            }
+            Step::Index(i) => path.push_str(&format!("[{}]", i)),
+        }
+    }
+    path
+}
+
+impl Query {
+    fn evaluate<'a>(&self, current: &'a Value, root: &'a Value) -> Vec<Node<'a>> {
+        let mut nodes = vec![(Vec::new(), current)];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for (path, value) in nodes {
+                match *segment {
+                    Segment::Child(ref selectors) => {
+                        select_all(selectors, &path, value, root, &mut next)
+                    }
+                    Segment::Descendant(ref selectors) => {
+                        for (path, value) in descendants(path, value) {
+                            select_all(selectors, &path, value, root, &mut next);
+                        }
+                    }
+                }
+            }
+            nodes = next;
+        }
+        nodes
+    }
+}
+
+// the node itself followed by everything beneath it, in document order
+fn descendants(path: Vec<Step>, value: &Value) -> Vec<Node<'_>> {
+    let mut found = Vec::new();
+    let mut stack = vec![(path, value)];
+    while let Some((path, value)) = stack.pop() {
+        let mut children = children(&path, value);
+        children.reverse();
+        found.push((path, value));
+        stack.extend(children);
+    }
+    found
+}
+
+fn children<'a>(path: &[Step], value: &'a Value) -> Vec<Node<'a>> {
+    match *value {
+        Value::Object(ref map) => map
+            .iter()
+            .map(|(k, v)| (child_path(path, Step::Key(k.clone())), v))
+            .collect(),
+        Value::Array(ref items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (child_path(path, Step::Index(i)), v))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn child_path(path: &[Step], step: Step) -> Vec<Step> {
+    let mut child = path.to_vec();
+    child.push(step);
+    child
+}
+
+fn select_all<'a>(
+    selectors: &[Selector],
+    path: &[Step],
+    value: &'a Value,
+    root: &'a Value,
+    out: &mut Vec<Node<'a>>,
+) {
+    for selector in selectors {
+        selector.select(path, value, root, out);
+    }
+}
+
+impl Selector {
+    fn select<'a>(
+        &self,
+        path: &[Step],
+        value: &'a Value,
+        root: &'a Value,
+        out: &mut Vec<Node<'a>>,
+    ) {
+        match *self {
+            Selector::Name(ref name) => {
+                if let Some(child) = value.as_object().and_then(|map| map.get(name)) {
+                    out.push((child_path(path, Step::Key(name.clone())), child));
+                }
+            }
+            Selector::Wildcard => out.extend(children(path, value)),
+            Selector::Index(index) => {
+                if let Some(items) = value.as_array() {
+                    let len = items.len() as i64;
+                    let i = if index < 0 { len + index } else { index };
+                    if i >= 0 && i < len {
+                        out.push((
+                            child_path(path, Step::Index(i as usize)),
+                            &items[i as usize],
+                        ));
+                    }
+                }
+            }
+            Selector::Slice(start, end, step) => {
+                if let Some(items) = value.as_array() {
+                    for i in slice_indices(items.len() as i64, start, end, step.unwrap_or(1)) {
+                        out.push((child_path(path, Step::Index(i)), &items[i]));
+                    }
+                }
+            }
+            Selector::Filter(ref expr) => {
+                for (path, child) in children(path, value) {
+                    if expr.test(child, root) {
+                        out.push((path, child));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).max(0).min(len);
+        let upper = normalize(end.unwrap_or(len)).max(0).min(len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            // a step past i64::MAX is past the end too
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).max(-1).min(len - 1);
+        let lower = end.map(normalize).unwrap_or(-len - 1).max(-1).min(len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+    indices
+}
+
+impl Expr {
+    fn test(&self, current: &Value, root: &Value) -> bool {
+        match *self {
+            Expr::Or(ref a, ref b) => a.test(current, root) || b.test(current, root),
+            Expr::And(ref a, ref b) => a.test(current, root) && b.test(current, root),
+            Expr::Not(ref e) => !e.test(current, root),
+            Expr::Exists(ref operand) => !operand.nodes(current, root).is_empty(),
+            Expr::Compare(ref a, op, ref b) => {
+                compare(a.singular(current, root), op, b.singular(current, root))
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn nodes<'a>(&'a self, current: &'a Value, root: &'a Value) -> Vec<&'a Value> {
+        match *self {
+            Operand::Literal(ref value) => vec![value],
+            Operand::Current(ref query) => query
+                .evaluate(current, root)
+                .into_iter()
+                .map(|n| n.1)
+                .collect(),
+            Operand::Root(ref query) => query
+                .evaluate(root, root)
+                .into_iter()
+                .map(|n| n.1)
+                .collect(),
+        }
+    }
+
+    // comparisons only make sense against a single value; anything else is "nothing"
+    fn singular<'a>(&'a self, current: &'a Value, root: &'a Value) -> Option<&'a Value> {
+        let mut nodes = self.nodes(current, root);
+        if nodes.len() == 1 {
+            nodes.pop()
+        } else {
+            None
+        }
+    }
+}
+
+fn compare(a: Option<&Value>, op: CompareOp, b: Option<&Value>) -> bool {
+    match op {
+        CompareOp::Eq => equal(a, b),
+        CompareOp::Ne => !equal(a, b),
+        CompareOp::Lt => less(a, b),
+        CompareOp::Le => less(a, b) || equal(a, b),
+        CompareOp::Gt => less(b, a),
+        CompareOp::Ge => less(b, a) || equal(a, b),
+    }
+}
+
+fn equal(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => json_eq(a, b),
+        _ => false,
+    }
+}
+
+fn less(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (Some(Value::Number(x)), Some(Value::Number(y))) => {
+            compare_numbers(x, y) == Some(Ordering::Less)
+        }
+        (Some(Value::String(x)), Some(Value::String(y))) => x < y,
+        _ => false,
+    }
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    pos: usize,
+}
+
+impl<'q> Parser<'q> {
+    fn error(&self, reason: &str) -> Error {
+        Error::InvalidJsonPath {
+            query: self.query.to_string(),
+            offset: self.pos,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.query[self.pos..].chars().next()
+    }
+
+    fn looking_at(&self, s: &str) -> bool {
+        self.query[self.pos..].starts_with(s)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        if self.looking_at(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ' ' || c == '\t' || c == '\n' || c == '\r' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            let start = self.pos;
+            self.skip_whitespace();
+            if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.bracket()?,
+                    Some('*') => {
+                        self.bump();
+                        vec![Selector::Wildcard]
+                    }
+                    _ => vec![Selector::Name(self.member_name()?)],
+                };
+                segments.push(Segment::Descendant(selectors));
+            } else if self.eat(".") {
+                if self.eat("*") {
+                    segments.push(Segment::Child(vec![Selector::Wildcard]));
+                } else {
+                    segments.push(Segment::Child(vec![Selector::Name(self.member_name()?)]));
+                }
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.bracket()?));
+            } else {
+                // whitespace only belongs to the query if a segment follows it
+                self.pos = start;
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn member_name(&mut self) -> Result<String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let first = self.pos == start;
+            if c == '_'
+                || c.is_ascii_alphabetic()
+                || !c.is_ascii()
+                || (!first && c.is_ascii_digit())
+            {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(self.error("expected a member name"));
+        }
+        Ok(self.query[start..self.pos].to_string())
+    }
+
+    fn bracket(&mut self) -> Result<Vec<Selector>> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some('\'') | Some('"') => Ok(Selector::Name(self.string_literal()?)),
+            Some('*') => {
+                self.bump();
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.bump();
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.or_expr()?))
+            }
+            _ => self.index_or_slice(),
+        }
+    }
+
+    fn index_or_slice(&mut self) -> Result<Selector> {
+        let start = self.optional_int()?;
+        self.skip_whitespace();
+        if !self.eat(":") {
+            return start
+                .map(Selector::Index)
+                .ok_or_else(|| self.error("expected a selector"));
+        }
+        self.skip_whitespace();
+        let end = self.optional_int()?;
+        self.skip_whitespace();
+        let step = if self.eat(":") {
+            self.skip_whitespace();
+            self.optional_int()?
+        } else {
+            None
+        };
+        Ok(Selector::Slice(start, end, step))
+    }
+
+    fn optional_int(&mut self) -> Result<Option<i64>> {
+        let start = self.pos;
+        self.eat("-");
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        match &self.query[start..self.pos] {
+            "" => Ok(None),
+            digits => digits
+                .parse()
+                .map(Some)
+                .map_err(|_| self.error("invalid integer")),
+        }
+    }
+
+    fn string_literal(&mut self) -> Result<String> {
+        let quote = self.bump().ok_or_else(|| self.error("expected a string"))?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        Some(c) if c == '\\' || c == '/' || c == '\'' || c == '"' => c,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.eat("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        ::std::char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        // from_str_radix alone would also take a sign, as in "+041"
+        let digits = self
+            .query
+            .get(self.pos..self.pos + 4)
+            .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()));
+        match digits.and_then(|d| u32::from_str_radix(d, 16).ok()) {
+            Some(code) => {
+                self.pos += 4;
+                Ok(code)
+            }
+            None => Err(self.error("expected four hex digits")),
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr> {
+        let mut expr = self.and_expr()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("||") {
+                return Ok(expr);
+            }
+            self.skip_whitespace();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+    }
+
+    fn and_expr(&mut self) -> Result<Expr> {
+        let mut expr = self.basic_expr()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("&&") {
+                return Ok(expr);
+            }
+            self.skip_whitespace();
+            expr = Expr::And(Box::new(expr), Box::new(self.basic_expr()?));
+        }
+    }
+
+    fn basic_expr(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            self.skip_whitespace();
+            return Ok(Expr::Not(Box::new(self.basic_expr()?)));
+        }
+        if self.eat("(") {
+            self.skip_whitespace();
+            let expr = self.or_expr()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(expr);
+        }
+        let left = self.operand()?;
+        self.skip_whitespace();
+        match self.compare_op() {
+            Some(op) => {
+                self.skip_whitespace();
+                Ok(Expr::Compare(left, op, self.operand()?))
+            }
+            None => match left {
+                Operand::Literal(_) => Err(self.error("expected a comparison operator")),
+                query => Ok(Expr::Exists(query)),
+            },
+        }
+    }
+
+    fn compare_op(&mut self) -> Option<CompareOp> {
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        for &(token, op) in &ops {
+            if self.eat(token) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.peek() {
+            Some('@') => {
+                self.bump();
+                Ok(Operand::Current(Query {
+                    segments: self.segments()?,
+                }))
+            }
+            Some('$') => {
+                self.bump();
+                Ok(Operand::Root(Query {
+                    segments: self.segments()?,
+                }))
+            }
+            Some('\'') | Some('"') => Ok(Operand::Literal(Value::String(self.string_literal()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number_literal(),
+            _ => {
+                for &(word, ref value) in &[
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                    ("null", Value::Null),
+                ] {
+                    if self.eat(word) {
+                        return Ok(Operand::Literal(value.clone()));
+                    }
+                }
+                Err(self.error("expected a query or a literal"))
+            }
+        }
+    }
+
+    fn number_literal(&mut self) -> Result<Operand> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            self.bump();
+        }
+        serde_json::from_str(&self.query[start..self.pos])
+            .map(Operand::Literal)
+            .map_err(|_| self.error("invalid number"))
+    }
+}
+
+impl fmt::Display for Match<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.path, self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Value {
+        json!({
+            "store": {
+                "book": [
+                    {"category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95},
+                    {"category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99},
+                    {"category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99},
+                    {"category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99}
+                ],
+                "bicycle": {"color": "red", "price": 399}
+            }
+        })
+    }
+
+    fn values(doc: &Value, path: &str) -> Vec<Value> {
+        JsonPath::parse(path)
+            .unwrap_or_else(|e| panic!("{}: {}", path, e))
+            .find_values(doc)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    fn paths(doc: &Value, path: &str) -> Vec<String> {
+        query(doc, path)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path)
+            .collect()
+    }
+
+    // (query, expected values in order) against the classic bookstore document;
+    // serde_json keeps object keys sorted, so "bicycle" is visited before "book"
+    #[test]
+    fn conformance_bookstore() {
+        let doc = store();
+        let cases: Vec<(&str, Value)> = vec![
+            (
+                "$.store.book[*].author",
+                json!([
+                    "Nigel Rees",
+                    "Evelyn Waugh",
+                    "Herman Melville",
+                    "J. R. R. Tolkien"
+                ]),
+            ),
+            (
+                "$..author",
+                json!([
+                    "Nigel Rees",
+                    "Evelyn Waugh",
+                    "Herman Melville",
+                    "J. R. R. Tolkien"
+                ]),
+            ),
+            ("$.store..price", json!([399, 8.95, 12.99, 8.99, 22.99])),
+            ("$..book[2].title", json!(["Moby Dick"])),
+            ("$..book[-1].title", json!(["The Lord of the Rings"])),
+            (
+                "$..book[0,1].title",
+                json!(["Sayings of the Century", "Sword of Honour"]),
+            ),
+            (
+                "$..book[:2].title",
+                json!(["Sayings of the Century", "Sword of Honour"]),
+            ),
+            (
+                "$..book[-2:].title",
+                json!(["Moby Dick", "The Lord of the Rings"]),
+            ),
+            ("$..book[::-1].price", json!([22.99, 8.99, 12.99, 8.95])),
+            (
+                "$..book[?(@.isbn)].title",
+                json!(["Moby Dick", "The Lord of the Rings"]),
+            ),
+            (
+                "$..book[?(!@.isbn)].title",
+                json!(["Sayings of the Century", "Sword of Honour"]),
+            ),
+            (
+                "$..book[?(@.price < 10)].title",
+                json!(["Sayings of the Century", "Moby Dick"]),
+            ),
+            (
+                "$..book[?@.price > 20 || @.category == 'reference'].title",
+                json!(["Sayings of the Century", "The Lord of the Rings"]),
+            ),
+            (
+                "$..book[?(@.category == \"fiction\" && @.price <= 12.99)].price",
+                json!([12.99, 8.99]),
+            ),
+            ("$..book[?(@.price > $.store.bicycle.price)]", json!([])),
+            ("$.store['bicycle']['color', 'price']", json!(["red", 399])),
+            ("$.store.bicycle.*", json!(["red", 399])),
+            ("$.store.missing", json!([])),
+            ("$.store.book[10]", json!([])),
+            ("$", json!([store()])),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(Value::Array(values(&doc, path)), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn conformance_slices() {
+        let doc = json!([0, 1, 2, 3, 4, 5, 6]);
+        let cases: Vec<(&str, Value)> = vec![
+            ("$[1:3]", json!([1, 2])),
+            ("$[5:]", json!([5, 6])),
+            ("$[1:5:2]", json!([1, 3])),
+            ("$[5:1:-2]", json!([5, 3])),
+            ("$[::-1]", json!([6, 5, 4, 3, 2, 1, 0])),
+            ("$[-3:-1]", json!([4, 5])),
+            ("$[0:100]", json!([0, 1, 2, 3, 4, 5, 6])),
+            ("$[::0]", json!([])),
+            ("$[3:1]", json!([])),
+            ("$[-1, 0]", json!([6, 0])),
+            ("$[ 1 : 2 ]", json!([1])),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(Value::Array(values(&doc, path)), expected, "{}", path);
+        }
+        let doc = json!([0, 1, 2, 3]);
+        let cases: Vec<(&str, Value)> = vec![
+            ("$[1::9223372036854775807]", json!([1])),
+            ("$[2::-9223372036854775808]", json!([2])),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(Value::Array(values(&doc, path)), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn conformance_filters() {
+        let doc = json!({"users": [
+            {"name": "ann", "age": 31, "meaningOfLife": 42, "tags": ["a"]},
+            {"name": "bob", "age": 29, "meaningOfLife": 7},
+            {"name": "cy", "age": 30.0, "meaningOfLife": null, "tags": []},
+            {"name": "di", "age": "old"}
+        ]});
+        let cases: Vec<(&str, Value)> = vec![
+            ("$.users[*].meaningOfLife", json!([42, 7, null])),
+            ("$.users[?(@.age > 30)].name", json!(["ann"])),
+            ("$.users[?(@.age >= 30)].name", json!(["ann", "cy"])),
+            ("$.users[?(@.age == 30)].name", json!(["cy"])),
+            ("$.users[?(@.age != 30)].name", json!(["ann", "bob", "di"])),
+            ("$.users[?(@.meaningOfLife == null)].name", json!(["cy"])),
+            (
+                "$.users[?(@.nope == @.nada)].name",
+                json!(["ann", "bob", "cy", "di"]),
+            ),
+            ("$.users[?(@.age < 'z')].name", json!(["di"])),
+            ("$.users[?(@.tags)].name", json!(["ann", "cy"])),
+            ("$.users[?(@.tags[0] == 'a')].name", json!(["ann"])),
+            (
+                "$.users[?(!(@.age > 29 && @.age < 31))].name",
+                json!(["ann", "bob", "di"]),
+            ),
+            ("$.users[?(@.age > -1e3)].name", json!(["ann", "bob", "cy"])),
+            ("$..[?(@ == 42)]", json!([42])),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(Value::Array(values(&doc, path)), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn conformance_recursive_descent() {
+        let doc = json!({"a": {"a": {"a": 1}}, "b": [{"a": 2}]});
+        assert_eq!(
+            values(&doc, "$..a"),
+            vec![json!({"a": {"a": 1}}), json!({"a": 1}), json!(1), json!(2)]
+        );
+        assert_eq!(values(&doc, "$..*").len(), 6);
+        assert_eq!(values(&doc, "$..[0]"), vec![json!({"a": 2})]);
+    }
+
+    #[test]
+    fn normalized_paths() {
+        let doc =
+            json!({"users": [{"meaningOfLife": 42}, {"meaningOfLife": 7}], "it's": {"a\\b": 1}});
+        assert_eq!(
+            paths(&doc, "$.users[*].meaningOfLife"),
+            vec![
+                "$['users'][0]['meaningOfLife']",
+                "$['users'][1]['meaningOfLife']"
+            ]
+        );
+        assert_eq!(paths(&doc, "$[\"it's\"].*"), vec!["$['it\\'s']['a\\\\b']"]);
+        assert_eq!(paths(&doc, "$"), vec!["$"]);
+    }
+
+    #[test]
+    fn quoted_names_and_escapes() {
+        let doc = json!({"a b": 1, "é": 2, "\u{263A}": 3, "\u{1F600}": 4});
+        assert_eq!(values(&doc, "$['a b']"), vec![json!(1)]);
+        assert_eq!(values(&doc, "$.é"), vec![json!(2)]);
+        assert_eq!(values(&doc, r#"$["☺"]"#), vec![json!(3)]);
+        assert_eq!(values(&doc, r#"$['😀']"#), vec![json!(4)]);
+        assert_eq!(values(&doc, r#"$['\u263a']"#), vec![json!(3)]);
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        for path in &[
+            "",
+            "store",
+            "$.",
+            "$[",
+            "$['a'",
+            "$[?(@.a ==)]",
+            "$[?(1)]",
+            "$.a b",
+            "$[1,]",
+            "$..",
+            "$.1a",
+            "$['\\x']",
+            "$[?(@.a > 1]",
+            "$['\\ud83d']",
+            "$['\\u+263']",
+            "$['\\u26']",
+            "$[length(@)]",
+        ] {
+            match JsonPath::parse(path) {
+                Err(Error::InvalidJsonPath { .. }) => (),
+                other => panic!("{:?} should not parse: {:?}", path, other),
+            }
+        }
+        match JsonPath::parse("$.a b") {
+            Err(Error::InvalidJsonPath { offset, .. }) => assert_eq!(offset, 4),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn queries_raw_input() {
+        let found =
+            query_input(r#"{"users": [{"meaningOfLife": 42}]}"#, "$..meaningOfLife").unwrap();
+        assert_eq!(
+            found,
+            vec![("$['users'][0]['meaningOfLife']".to_string(), json!(42))]
+        );
+        assert!(query_input("'asdf'", "$").is_err());
+    }
+}
@@ -0,0 +1,323 @@
+// hardening for untrusted input: the streaming reader checks every limit as it reads, so an
+// oversized document is rejected before it has been buffered, let alone built
+use std::io::Read;
+
+use serde_json::Value;
+
+use chapter_3::stream::EventReader;
+use chapter_3::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    Error,
+    // keep the first value and skip the rest
+    First,
+    // keep the last one, which is what serde_json does
+    Last,
+}
+
+// "big" means an integer outside both i64 and u64, or a number outside f64's range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BigNumbers {
+    // round big integers to f64 like serde_json; numbers past f64's range still fail
+    Lossy,
+    Reject,
+    // keep the number's text as a string value
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) max_depth: usize,
+    pub(crate) max_input_bytes: usize,
+    pub(crate) max_string_length: usize,
+    pub(crate) max_entries: usize,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) big_numbers: BigNumbers,
+}
+
+// the defaults behave like parse_input_to_json_value, including serde_json's depth of 128
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            max_depth: 128,
+            max_input_bytes: usize::MAX,
+            max_string_length: usize::MAX,
+            max_entries: usize::MAX,
+            duplicate_keys: DuplicateKeys::Last,
+            big_numbers: BigNumbers::Lossy,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    // a starting point for input from outside: a few MB, shallow, and nothing ambiguous
+    pub fn untrusted() -> ParseOptions {
+        ParseOptions {
+            max_depth: 64,
+            max_input_bytes: 4 * 1024 * 1024,
+            max_string_length: 64 * 1024,
+            max_entries: 10_000,
+            duplicate_keys: DuplicateKeys::Error,
+            big_numbers: BigNumbers::Reject,
+        }
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> ParseOptions {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn max_input_bytes(mut self, bytes: usize) -> ParseOptions {
+        self.max_input_bytes = bytes;
+        self
+    }
+
+    // in bytes after unescaping; applies to keys and to the digits of numbers as well
+    pub fn max_string_length(mut self, bytes: usize) -> ParseOptions {
+        self.max_string_length = bytes;
+        self
+    }
+
+    // per array or object
+    pub fn max_entries(mut self, entries: usize) -> ParseOptions {
+        self.max_entries = entries;
+        self
+    }
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> ParseOptions {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    pub fn big_numbers(mut self, policy: BigNumbers) -> ParseOptions {
+        self.big_numbers = policy;
+        self
+    }
+}
+
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Value> {
+    parse_reader_with_options(input.as_bytes(), options)
+}
+
+pub fn parse_reader_with_options<R: Read>(input: R, options: &ParseOptions) -> Result<Value> {
+    let mut reader = EventReader::with_options(input, options.clone());
+    let value = reader
+        .next_value()?
+        .expect("the first read either yields a value or fails");
+    // only whitespace may follow
+    reader.next_event()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chapter_3::parse_input_to_json_value;
+    use error::Error;
+    use std::io::{self, Read};
+
+    // repeats a pattern forever, counting how much of it the parser pulled in
+    struct Endless {
+        prefix: Vec<u8>,
+        pattern: Vec<u8>,
+        read: usize,
+    }
+
+    impl Endless {
+        fn new(prefix: &str, pattern: &str) -> Endless {
+            Endless {
+                prefix: prefix.as_bytes().to_vec(),
+                pattern: pattern.as_bytes().to_vec(),
+                read: 0,
+            }
+        }
+    }
+
+    impl Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            for b in buf.iter_mut() {
+                *b = if self.read < self.prefix.len() {
+                    self.prefix[self.read]
+                } else {
+                    let i = (self.read - self.prefix.len()) % self.pattern.len();
+                    self.pattern[i]
+                };
+                self.read += 1;
+            }
+            Ok(buf.len())
+        }
+    }
+
+    fn rejected(result: Result<Value>) -> String {
+        match result {
+            Err(Error::LimitExceeded { reason, .. }) => reason,
+            other => panic!("expected a limit error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stops_endless_input_at_each_limit() {
+        // BufReader pulls 8 KiB at a time, so that's the most that can be read past a limit
+        let bound = 64 * 1024 + 8 * 1024;
+        let cases = [
+            ("", "[", ParseOptions::new().max_depth(1_000), "nesting"),
+            (
+                "",
+                " ",
+                ParseOptions::new().max_input_bytes(64 * 1024),
+                "input",
+            ),
+            (
+                "[\"",
+                "a",
+                ParseOptions::new().max_string_length(64 * 1024),
+                "string",
+            ),
+            (
+                "[",
+                "1",
+                ParseOptions::new().max_string_length(1024),
+                "number",
+            ),
+            (
+                "[",
+                "0,",
+                ParseOptions::new().max_entries(10_000),
+                "entries",
+            ),
+            (
+                "{",
+                "\"k\":0,",
+                ParseOptions::new().max_entries(10_000),
+                "entries",
+            ),
+        ];
+        for (prefix, pattern, options, expected) in cases.iter() {
+            let mut input = Endless::new(prefix, pattern);
+            let reason = rejected(parse_reader_with_options(&mut input, options));
+            assert!(reason.contains(expected), "{}", reason);
+            assert!(
+                input.read <= bound,
+                "read {} bytes for {}",
+                input.read,
+                reason
+            );
+        }
+    }
+
+    #[test]
+    fn deep_nesting_fails_without_overflowing_the_stack() {
+        let input = "[".repeat(1_000_000);
+        assert!(rejected(parse_with_options(&input, &ParseOptions::new())).contains("128"));
+        let nested = format!("{}{}", "[".repeat(64), "]".repeat(64));
+        assert!(parse_with_options(&nested, &ParseOptions::untrusted()).is_ok());
+        let deeper = format!("{}{}", "[".repeat(65), "]".repeat(65));
+        assert!(parse_with_options(&deeper, &ParseOptions::untrusted()).is_err());
+    }
+
+    #[test]
+    fn limits_are_inclusive() {
+        let options = ParseOptions::new()
+            .max_string_length(3)
+            .max_entries(2)
+            .max_input_bytes(12);
+        assert_eq!(
+            parse_with_options(r#"["abc","d"]"#, &options).unwrap(),
+            json!(["abc", "d"])
+        );
+        assert!(parse_with_options(r#"["abcd"]"#, &options).is_err());
+        assert!(parse_with_options(r#"[1,2,3]"#, &options).is_err());
+        assert!(parse_with_options(r#"["a",  "b"]  "#, &options).is_err());
+        // an escape counts as the character it stands for
+        assert!(parse_with_options(r#"["\u00e9"]"#, &options).is_ok());
+    }
+
+    #[test]
+    fn applies_the_duplicate_key_policy() {
+        let input = r#"{"meaningOfLife": 41, "meaningOfLife": {"deep": [42]}}"#;
+        let parse = |policy| parse_with_options(input, &ParseOptions::new().duplicate_keys(policy));
+        assert!(rejected(parse(DuplicateKeys::Error)).contains("\"meaningOfLife\""));
+        assert_eq!(
+            parse(DuplicateKeys::First).unwrap(),
+            json!({"meaningOfLife": 41})
+        );
+        assert_eq!(
+            parse(DuplicateKeys::Last).unwrap(),
+            parse_input_to_json_value(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn applies_the_big_number_policy() {
+        let input =
+            "[18446744073709551616, -9223372036854775809, 1e400, 1.5, 18446744073709551615]";
+        let parse = |policy| parse_with_options(input, &ParseOptions::new().big_numbers(policy));
+        assert!(rejected(parse(BigNumbers::Reject)).contains("18446744073709551616"));
+        assert_eq!(
+            parse(BigNumbers::String).unwrap(),
+            json!([
+                "18446744073709551616",
+                "-9223372036854775809",
+                "1e400",
+                1.5,
+                18446744073709551615_u64
+            ])
+        );
+        // lossy matches serde_json: big integers round, 1e400 is still an error
+        assert!(matches!(parse(BigNumbers::Lossy), Err(Error::Parse(_))));
+        assert_eq!(
+            parse_with_options("[18446744073709551616]", &ParseOptions::new()).unwrap(),
+            parse_input_to_json_value("[18446744073709551616]").unwrap()
+        );
+    }
+
+    #[test]
+    fn agrees_with_serde_json_on_random_input() {
+        // xorshift, so the corpus is the same on every run
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let pieces = [
+            "{",
+            "}",
+            "[",
+            "]",
+            ",",
+            ":",
+            "\"a\"",
+            "\"b\"",
+            "1",
+            "-2.5e3",
+            "true",
+            "null",
+            " ",
+            "\"\\u00e9\"",
+            "0",
+            "x",
+            "\"",
+        ];
+        let options = ParseOptions::new();
+        for _ in 0..20_000 {
+            let len = (next() % 12) as usize;
+            let input: String = (0..len)
+                .map(|_| pieces[(next() % pieces.len() as u64) as usize])
+                .collect();
+            let ours = parse_with_options(&input, &options);
+            let theirs = parse_input_to_json_value(&input);
+            match (ours, theirs) {
+                (Ok(a), Ok(b)) => assert_eq!(a, b, "{:?}", input),
+                (Err(_), Err(_)) => (),
+                (a, b) => panic!("{:?}: {:?} vs {:?}", input, a, b),
+            }
+        }
+    }
+}
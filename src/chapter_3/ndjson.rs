@@ -0,0 +1,420 @@
+// newline-delimited JSON: one record per line, read lazily, where a bad line is reported with
+// its number and the batch carries on
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use serde_json::Value;
+
+use chapter_3::limits::parse_with_options;
+use chapter_3::printer::minify;
+use chapter_3::{extract_from, parse_input_to_json_value, ParseOptions, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    // 1-based, counting blank lines too so it matches an editor
+    pub line: usize,
+    pub value: Value,
+}
+
+#[derive(Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub error: Error,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl error::Error for LineError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+pub struct Reader<R> {
+    input: R,
+    line: usize,
+    options: Option<ParseOptions>,
+    finished: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Reader<R> {
+        Reader {
+            input,
+            line: 0,
+            options: None,
+            finished: false,
+        }
+    }
+
+    // parse each line with limits, eg. ParseOptions::untrusted() for data from outside
+    pub fn with_options(input: R, options: ParseOptions) -> Reader<R> {
+        Reader {
+            options: Some(options),
+            ..Reader::new(input)
+        }
+    }
+
+    // max_input_bytes is per line, not counting the '\n', so one huge record can't exhaust
+    // memory before it's parsed
+    fn line_limit(&self) -> usize {
+        self.options
+            .as_ref()
+            .map_or(usize::MAX, |options| options.max_input_bytes)
+    }
+
+    // drops what's left of an overlong line without holding on to it
+    fn skip_line(&mut self) -> io::Result<()> {
+        loop {
+            let (found, used) = {
+                let available = self.input.fill_buf()?;
+                match available.iter().position(|&b| b == b'\n') {
+                    Some(i) => (true, i + 1),
+                    None => (available.is_empty(), available.len()),
+                }
+            };
+            self.input.consume(used);
+            if found {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse(&self, line: &str) -> Result<Value> {
+        match self.options {
+            Some(ref options) => parse_with_options(line, options),
+            None => parse_input_to_json_value(line),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = ::std::result::Result<Record, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let mut buf = Vec::new();
+            self.line += 1;
+            let limit = self.line_limit();
+            let read = (&mut self.input)
+                .take((limit as u64).saturating_add(1))
+                .read_until(b'\n', &mut buf);
+            match read {
+                Ok(0) => self.finished = true,
+                // an unreadable input can't be resumed, so this is the last item
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(LineError {
+                        line: self.line,
+                        error: Error::Io(e),
+                    }));
+                }
+                Ok(_) if buf.len() - buf.ends_with(b"\n") as usize > limit => {
+                    let skipped = if buf.ends_with(b"\n") {
+                        Ok(())
+                    } else {
+                        self.skip_line()
+                    };
+                    let error = match skipped {
+                        Ok(()) => Error::LimitExceeded {
+                            line: 1,
+                            column: limit + 1,
+                            reason: format!("line longer than {} bytes", limit),
+                        },
+                        Err(e) => {
+                            self.finished = true;
+                            Error::Io(e)
+                        }
+                    };
+                    return Some(Err(LineError {
+                        line: self.line,
+                        error,
+                    }));
+                }
+                Ok(_) => {
+                    let text = match String::from_utf8(buf) {
+                        Ok(text) => text,
+                        Err(_) => {
+                            return Some(Err(LineError {
+                                line: self.line,
+                                error: Error::Syntax {
+                                    line: 1,
+                                    column: 1,
+                                    reason: "invalid UTF-8".to_string(),
+                                },
+                            }))
+                        }
+                    };
+                    let trimmed = text.trim_end_matches(['\n', '\r']);
+                    if trimmed.trim().is_empty() {
+                        continue;
+                    }
+                    let line = self.line;
+                    return Some(
+                        self.parse(trimmed)
+                            .map(|value| Record { line, value })
+                            .map_err(|error| LineError { line, error }),
+                    );
+                }
+            }
+        }
+        None
+    }
+}
+
+// runs the extractor over every record as it's read; parse and extraction failures both come
+// through as line errors
+pub fn extract_each<R, T, F>(
+    reader: Reader<R>,
+    mut extract: F,
+) -> impl Iterator<Item = ::std::result::Result<(usize, T), LineError>>
+where
+    R: BufRead,
+    F: FnMut(&Value) -> Result<T>,
+{
+    reader.map(move |record| {
+        let record = record?;
+        extract(&record.value)
+            .map(|value| (record.line, value))
+            .map_err(|error| LineError {
+                line: record.line,
+                error,
+            })
+    })
+}
+
+#[derive(Debug)]
+pub struct Summary<T> {
+    pub records: usize,
+    // the extracted values with the line each came from
+    pub values: Vec<(usize, T)>,
+    pub errors: Vec<LineError>,
+}
+
+impl<T> Summary<T> {
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl<T> fmt::Display for Summary<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} record(s): {} ok, {} failed",
+            self.records,
+            self.values.len(),
+            self.errors.len()
+        )?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn summarize<R, T, F>(reader: Reader<R>, extract: F) -> Summary<T>
+where
+    R: BufRead,
+    F: FnMut(&Value) -> Result<T>,
+{
+    let mut summary = Summary {
+        records: 0,
+        values: Vec::new(),
+        errors: Vec::new(),
+    };
+    for result in extract_each(reader, extract) {
+        summary.records += 1;
+        match result {
+            Ok(value) => summary.values.push(value),
+            Err(error) => summary.errors.push(error),
+        }
+    }
+    summary
+}
+
+// get_meaning_of_life over every record
+pub fn summarize_meaning_of_life<R: BufRead>(input: R) -> Summary<i64> {
+    summarize(Reader::new(input), |value| {
+        extract_from(value, "/meaningOfLife")
+    })
+}
+
+pub struct Writer<W> {
+    output: W,
+    lines: usize,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(output: W) -> Writer<W> {
+        Writer { output, lines: 0 }
+    }
+
+    // minified, so a record can never span lines
+    pub fn write(&mut self, value: &Value) -> Result<()> {
+        let mut line = minify(value);
+        line.push('\n');
+        self.output.write_all(line.as_bytes())?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.output.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, BufReader, Cursor, Read};
+
+    const TELEMETRY: &str = "{\"meaningOfLife\": 42}\n\n{\"meaningOfLife\": 7}\r\n'asdf'\n{\"meaningOfLife\": \"42\"}\n{\"other\": 1}\n[1, 2";
+
+    #[test]
+    fn reads_records_with_line_numbers() {
+        let results: Vec<_> = Reader::new(Cursor::new(TELEMETRY)).collect();
+        assert_eq!(results.len(), 6);
+        assert_eq!(
+            *results[0].as_ref().unwrap(),
+            Record {
+                line: 1,
+                value: json!({"meaningOfLife": 42})
+            }
+        );
+        assert_eq!(results[1].as_ref().unwrap().line, 3);
+        let failed: Vec<usize> = results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .map(|e| e.line)
+            .collect();
+        assert_eq!(failed, vec![4, 7]);
+        assert!(results[2]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 4: invalid JSON"));
+    }
+
+    #[test]
+    fn summarizes_an_extractor_without_stopping() {
+        let summary = summarize_meaning_of_life(Cursor::new(TELEMETRY));
+        assert_eq!(summary.records, 6);
+        assert_eq!(summary.values, vec![(1, 42), (3, 7)]);
+        let errors: Vec<String> = summary.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 4);
+        assert!(errors[1].starts_with("line 5: expected i64 at /meaningOfLife, found string"));
+        assert!(errors[2].starts_with("line 6: missing key at /meaningOfLife"));
+        assert!(!summary.is_clean());
+        assert!(summary
+            .to_string()
+            .starts_with("6 record(s): 2 ok, 4 failed\n  line 4: "));
+        let total: i64 = summary.values.iter().map(|&(_, v)| v).sum();
+        assert_eq!(total, 49);
+    }
+
+    #[test]
+    fn processes_lazily() {
+        // an endless stream of records; taking a few must not try to read them all
+        struct Endless;
+        impl Read for Endless {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let record = b"{\"meaningOfLife\": 42}\n";
+                let n = buf.len().min(record.len());
+                buf[..n].copy_from_slice(&record[..n]);
+                Ok(n)
+            }
+        }
+        let first: Vec<(usize, i64)> = extract_each(Reader::new(BufReader::new(Endless)), |v| {
+            extract_from(v, "/meaningOfLife")
+        })
+        .take(3)
+        .collect::<::std::result::Result<_, _>>()
+        .unwrap();
+        assert_eq!(first, vec![(1, 42), (2, 42), (3, 42)]);
+    }
+
+    #[test]
+    fn applies_parse_options_per_line() {
+        let input = "[1]\n[[[1]]]\n{\"a\": 1, \"a\": 2}\n";
+        let options = ParseOptions::new()
+            .max_depth(2)
+            .duplicate_keys(::chapter_3::limits::DuplicateKeys::Error);
+        let lines: Vec<_> = Reader::with_options(Cursor::new(input), options)
+            .map(|r| r.map_err(|e| e.line))
+            .collect();
+        assert!(lines[0].is_ok());
+        assert_eq!(lines[1], Err(2));
+        assert_eq!(lines[2], Err(3));
+    }
+
+    #[test]
+    fn rejects_oversized_lines_and_keeps_going() {
+        let long = format!("[{}1]", "1, ".repeat(10_000));
+        let input = format!("[1]\n{}\n{}\r\n[2, 3]", long, long);
+        let options = ParseOptions::new().max_input_bytes(6);
+        let lines: Vec<_> = Reader::with_options(Cursor::new(input), options).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].as_ref().unwrap().value, json!([1]));
+        for (i, line) in [(1, &lines[1]), (2, &lines[2])] {
+            match *line {
+                Err(LineError {
+                    line,
+                    error: Error::LimitExceeded { ref reason, .. },
+                }) => {
+                    assert_eq!(line, i + 1);
+                    assert_eq!(reason, "line longer than 6 bytes");
+                }
+                ref other => panic!("unexpected {:?}", other),
+            }
+        }
+        // exactly at the limit is fine, with or without the newline
+        assert_eq!(lines[3].as_ref().unwrap().value, json!([2, 3]));
+        let options = ParseOptions::new().max_input_bytes(6);
+        let mut exact = Reader::with_options(Cursor::new("[2, 3]\n"), options);
+        assert_eq!(exact.next().unwrap().unwrap().value, json!([2, 3]));
+    }
+
+    #[test]
+    fn reports_invalid_utf8_and_keeps_going() {
+        let input: &[u8] = b"{\"meaningOfLife\": 1}\n\xff\xfe\n{\"meaningOfLife\": 2}\n";
+        let summary = summarize_meaning_of_life(input);
+        assert_eq!(summary.values, vec![(1, 1), (3, 2)]);
+        assert_eq!(summary.errors[0].line, 2);
+    }
+
+    #[test]
+    fn writes_one_record_per_line() {
+        let mut writer = Writer::new(Vec::new());
+        for value in &[json!({"meaningOfLife": 42, "note": "a\nb"}), json!([1, 2])] {
+            writer.write(value).unwrap();
+        }
+        assert_eq!(writer.lines(), 2);
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            written,
+            "{\"meaningOfLife\":42,\"note\":\"a\\nb\"}\n[1,2]\n"
+        );
+        let read: Vec<Value> = Reader::new(Cursor::new(written))
+            .map(|r| r.unwrap().value)
+            .collect();
+        assert_eq!(
+            read,
+            vec![json!({"meaningOfLife": 42, "note": "a\nb"}), json!([1, 2])]
+        );
+    }
+}
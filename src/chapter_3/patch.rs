@@ -0,0 +1,750 @@
+// JSON Patch (RFC 6902) and JSON Merge Patch (RFC 7386)
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use chapter_3::pointer::array_index;
+use chapter_3::{json_eq, parse_input_to_json_value, type_name, Pointer, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add { path: Pointer, value: Value },
+    Remove { path: Pointer },
+    Replace { path: Pointer, value: Value },
+    Move { from: Pointer, path: Pointer },
+    Copy { from: Pointer, path: Pointer },
+    Test { path: Pointer, value: Value },
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch(pub Vec<Operation>);
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Operation::Add { .. } => "add",
+            Operation::Remove { .. } => "remove",
+            Operation::Replace { .. } => "replace",
+            Operation::Move { .. } => "move",
+            Operation::Copy { .. } => "copy",
+            Operation::Test { .. } => "test",
+        }
+    }
+
+    fn from_value(op: &Value) -> ::std::result::Result<Operation, String> {
+        let op = op
+            .as_object()
+            .ok_or_else(|| format!("expected an object, found {}", type_name(op)))?;
+        let pointer = |field: &str| -> ::std::result::Result<Pointer, String> {
+            let raw = op
+                .get(field)
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("missing string field {:?}", field))?;
+            Pointer::parse(raw).map_err(|e| e.to_string())
+        };
+        let value = || -> ::std::result::Result<Value, String> {
+            op.get("value")
+                .cloned()
+                .ok_or_else(|| "missing field \"value\"".to_string())
+        };
+        Ok(match op.get("op").and_then(Value::as_str) {
+            Some("add") => Operation::Add {
+                path: pointer("path")?,
+                value: value()?,
+            },
+            Some("remove") => Operation::Remove {
+                path: pointer("path")?,
+            },
+            Some("replace") => Operation::Replace {
+                path: pointer("path")?,
+                value: value()?,
+            },
+            Some("move") => Operation::Move {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            Some("copy") => Operation::Copy {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            Some("test") => Operation::Test {
+                path: pointer("path")?,
+                value: value()?,
+            },
+            Some(other) => return Err(format!("unknown op {:?}", other)),
+            None => return Err("missing string field \"op\"".to_string()),
+        })
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut op = Map::new();
+        op.insert("op".to_string(), Value::String(self.name().to_string()));
+        match *self {
+            Operation::Add {
+                ref path,
+                ref value,
+            }
+            | Operation::Replace {
+                ref path,
+                ref value,
+            }
+            | Operation::Test {
+                ref path,
+                ref value,
+            } => {
+                op.insert("path".to_string(), Value::String(path.to_string()));
+                op.insert("value".to_string(), value.clone());
+            }
+            Operation::Remove { ref path } => {
+                op.insert("path".to_string(), Value::String(path.to_string()));
+            }
+            Operation::Move { ref from, ref path } | Operation::Copy { ref from, ref path } => {
+                op.insert("from".to_string(), Value::String(from.to_string()));
+                op.insert("path".to_string(), Value::String(path.to_string()));
+            }
+        }
+        Value::Object(op)
+    }
+
+    fn apply(&self, doc: &mut Value) -> Result<()> {
+        match *self {
+            Operation::Add {
+                ref path,
+                ref value,
+            } => add(doc, path, value.clone()),
+            Operation::Remove { ref path } => remove(doc, path).map(|_| ()),
+            Operation::Replace {
+                ref path,
+                ref value,
+            } => {
+                *path.resolve_mut(doc)? = value.clone();
+                Ok(())
+            }
+            Operation::Move { ref from, ref path } => {
+                if path.starts_with(from) && path != from {
+                    return Err(failed(
+                        "a value can't be moved into one of its own children",
+                    ));
+                }
+                let value = remove(doc, from)?;
+                add(doc, path, value)
+            }
+            Operation::Copy { ref from, ref path } => {
+                let value = from.resolve(doc)?.clone();
+                add(doc, path, value)
+            }
+            Operation::Test {
+                ref path,
+                ref value,
+            } => {
+                let actual = path.resolve(doc)?;
+                if json_eq(actual, value) {
+                    Ok(())
+                } else {
+                    Err(failed(&format!(
+                        "test failed at {}: expected {}, found {}",
+                        path, value, actual
+                    )))
+                }
+            }
+        }
+    }
+}
+
+// placeholder for the reason; Patch::apply adds which operation it was
+fn failed(reason: &str) -> Error {
+    Error::Patch {
+        index: 0,
+        reason: reason.to_string(),
+    }
+}
+
+fn add(doc: &mut Value, path: &Pointer, value: Value) -> Result<()> {
+    let (parent, last) = match path.split_last() {
+        Some(split) => split,
+        None => {
+            *doc = value;
+            return Ok(());
+        }
+    };
+    match *parent.resolve_mut(doc)? {
+        Value::Object(ref mut map) => {
+            map.insert(last.to_string(), value);
+            Ok(())
+        }
+        Value::Array(ref mut items) => {
+            let index = if last == "-" {
+                items.len()
+            } else {
+                array_index(last)
+                    .filter(|&i| i <= items.len())
+                    .ok_or_else(|| {
+                        failed(&format!("index {:?} is out of bounds at {}", last, parent))
+                    })?
+            };
+            items.insert(index, value);
+            Ok(())
+        }
+        ref other => Err(Error::WrongType {
+            path: parent.to_string(),
+            expected: "object or array",
+            found: type_name(other),
+        }),
+    }
+}
+
+fn remove(doc: &mut Value, path: &Pointer) -> Result<Value> {
+    let (parent, last) = path
+        .split_last()
+        .ok_or_else(|| failed("the whole document can't be removed"))?;
+    // resolving the full path first gives the same MissingKey error resolve does
+    path.resolve(doc)?;
+    Ok(match *parent.resolve_mut(doc)? {
+        Value::Object(ref mut map) => map.remove(last),
+        Value::Array(ref mut items) => array_index(last).map(|i| items.remove(i)),
+        _ => None,
+    }
+    .expect("path was already resolved"))
+}
+
+impl Patch {
+    pub fn from_value(patch: &Value) -> Result<Patch> {
+        let ops = patch.as_array().ok_or_else(|| Error::Patch {
+            index: 0,
+            reason: format!("a patch must be an array, found {}", type_name(patch)),
+        })?;
+        ops.iter()
+            .enumerate()
+            .map(|(index, op)| {
+                Operation::from_value(op).map_err(|reason| Error::Patch { index, reason })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Patch)
+    }
+
+    pub fn parse(input: &str) -> Result<Patch> {
+        Patch::from_value(&parse_input_to_json_value(input)?)
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Array(self.0.iter().map(Operation::to_value).collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // all or nothing: the operations run against a copy, which only replaces doc if every one succeeds
+    pub fn apply(&self, doc: &mut Value) -> Result<()> {
+        let mut patched = doc.clone();
+        for (index, op) in self.0.iter().enumerate() {
+            op.apply(&mut patched).map_err(|e| Error::Patch {
+                index,
+                reason: match e {
+                    Error::Patch { reason, .. } => reason,
+                    other => format!("{} failed: {}", op.name(), other),
+                },
+            })?;
+        }
+        *doc = patched;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+// RFC 7386: objects merge key by key, null deletes, anything else replaces
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match *patch {
+        Value::Object(ref patch) => patch,
+        ref other => {
+            *target = other.clone();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let target = target
+        .as_object_mut()
+        .expect("target was just made an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// the merge patch that turns a into b; nulls inside b can't be expressed and are dropped
+pub fn merge_diff(a: &Value, b: &Value) -> Value {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut patch = Map::new();
+            for key in a.keys().filter(|k| !b.contains_key(*k)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, value) in b {
+                match a.get(key) {
+                    Some(old) if json_eq(old, value) => (),
+                    Some(old) => {
+                        patch.insert(key.clone(), merge_diff(old, value));
+                    }
+                    None => {
+                        patch.insert(key.clone(), merge_diff(&Value::Null, value));
+                    }
+                }
+            }
+            Value::Object(patch)
+        }
+        // a fresh object is written out as a patch so nested nulls are dropped, not kept
+        (_, Value::Object(_)) => merge_diff(&Value::Object(Map::new()), b),
+        _ => b.clone(),
+    }
+}
+
+// the largest LCS table diff_arrays will build, about 8MB; past it arrays are diffed by index
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+// a small patch that turns a into b; arrays are matched up by longest common subsequence
+pub fn diff(a: &Value, b: &Value) -> Patch {
+    let mut ops = Vec::new();
+    diff_values(&Pointer::root(), a, b, &mut ops);
+    Patch(ops)
+}
+
+fn diff_values(path: &Pointer, a: &Value, b: &Value, ops: &mut Vec<Operation>) {
+    if a == b {
+        return;
+    }
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for key in a.keys().filter(|k| !b.contains_key(*k)) {
+                ops.push(Operation::Remove {
+                    path: path.child(key.as_str()),
+                });
+            }
+            for (key, new) in b {
+                match a.get(key) {
+                    Some(old) => diff_values(&path.child(key.as_str()), old, new, ops),
+                    None => ops.push(Operation::Add {
+                        path: path.child(key.as_str()),
+                        value: new.clone(),
+                    }),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => diff_arrays(path, a, b, ops),
+        _ => ops.push(Operation::Replace {
+            path: path.clone(),
+            value: b.clone(),
+        }),
+    }
+}
+
+fn diff_arrays(path: &Pointer, a: &[Value], b: &[Value], ops: &mut Vec<Operation>) {
+    let prefix = a.iter().zip(b).take_while(|&(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|&(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a.len(), b.len());
+    if (n + 1).saturating_mul(m + 1) > MAX_LCS_CELLS {
+        return diff_by_index(path, prefix, a, b, ops);
+    }
+
+    // lcs[i][j] is the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // index tracks where we are in the array as the earlier operations have left it
+    let (mut i, mut j, mut index) = (0, 0, prefix);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            i += 1;
+            j += 1;
+            index += 1;
+        } else if i < n && j < m && lcs[i + 1][j + 1] == lcs[i][j] {
+            diff_values(&path.child(index.to_string()), &a[i], &b[j], ops);
+            i += 1;
+            j += 1;
+            index += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] == lcs[i][j]) {
+            ops.push(Operation::Add {
+                path: path.child(index.to_string()),
+                value: b[j].clone(),
+            });
+            j += 1;
+            index += 1;
+        } else {
+            ops.push(Operation::Remove {
+                path: path.child(index.to_string()),
+            });
+            i += 1;
+        }
+    }
+}
+
+// pairs up the elements position by position, then trims or extends the tail; never minimal
+// when something was inserted near the front, but linear however long the arrays are
+fn diff_by_index(
+    path: &Pointer,
+    offset: usize,
+    a: &[Value],
+    b: &[Value],
+    ops: &mut Vec<Operation>,
+) {
+    for (k, (old, new)) in a.iter().zip(b).enumerate() {
+        diff_values(&path.child((offset + k).to_string()), old, new, ops);
+    }
+    for _ in b.len()..a.len() {
+        ops.push(Operation::Remove {
+            path: path.child((offset + b.len()).to_string()),
+        });
+    }
+    for (k, new) in b.iter().enumerate().skip(a.len()) {
+        ops.push(Operation::Add {
+            path: path.child((offset + k).to_string()),
+            value: new.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patched(doc: Value, patch: Value) -> Result<Value> {
+        let mut doc = doc;
+        Patch::from_value(&patch)?.apply(&mut doc)?;
+        Ok(doc)
+    }
+
+    // the examples from RFC 6902 appendix A
+    #[test]
+    fn rfc6902_examples() {
+        let cases = vec![
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux"}]),
+                json!({"baz": "qux", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": ["bar", "baz"]}),
+                json!([{"op": "add", "path": "/foo/1", "value": "qux"}]),
+                json!({"foo": ["bar", "qux", "baz"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "remove", "path": "/baz"}]),
+                json!({"foo": "bar"}),
+            ),
+            (
+                json!({"foo": ["bar", "qux", "baz"]}),
+                json!([{"op": "remove", "path": "/foo/1"}]),
+                json!({"foo": ["bar", "baz"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "replace", "path": "/baz", "value": "boo"}]),
+                json!({"baz": "boo", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}),
+                json!([{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]),
+                json!({"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}),
+            ),
+            (
+                json!({"foo": ["all", "grass", "cows", "eat"]}),
+                json!([{"op": "move", "from": "/foo/1", "path": "/foo/3"}]),
+                json!({"foo": ["all", "cows", "eat", "grass"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+                json!([{"op": "test", "path": "/baz", "value": "qux"}, {"op": "test", "path": "/foo/1", "value": 2}]),
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+            ),
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/child", "value": {"grandchild": {}}}]),
+                json!({"foo": "bar", "child": {"grandchild": {}}}),
+            ),
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]),
+                json!({"foo": "bar", "baz": "qux"}),
+            ),
+            (
+                json!({"foo": ["bar"]}),
+                json!([{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]),
+                json!({"foo": ["bar", ["abc", "def"]]}),
+            ),
+            (
+                json!({"/": 9, "~1": 10}),
+                json!([{"op": "test", "path": "/~01", "value": 10}]),
+                json!({"/": 9, "~1": 10}),
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "copy", "from": "/foo", "path": "/bar"}]),
+                json!({"foo": 1, "bar": 1}),
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "replace", "path": "", "value": [1]}]),
+                json!([1]),
+            ),
+        ];
+        for (doc, patch, expected) in cases {
+            assert_eq!(patched(doc, patch.clone()).unwrap(), expected, "{}", patch);
+        }
+    }
+
+    #[test]
+    fn rfc6902_errors() {
+        let cases = vec![
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz/bat", "value": "qux"}]),
+            ),
+            (
+                json!({"/": 9, "~1": 10}),
+                json!([{"op": "test", "path": "/~01", "value": "10"}]),
+            ),
+            (
+                json!({"foo": [1]}),
+                json!([{"op": "add", "path": "/foo/2", "value": 2}]),
+            ),
+            (
+                json!({"foo": [1]}),
+                json!([{"op": "remove", "path": "/foo/-"}]),
+            ),
+            (
+                json!({"foo": {"a": 1}}),
+                json!([{"op": "move", "from": "/foo", "path": "/foo/a/b"}]),
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "replace", "path": "/bar", "value": 1}]),
+            ),
+            (json!({"foo": 1}), json!([{"op": "remove", "path": ""}])),
+        ];
+        for (doc, patch) in cases {
+            match patched(doc, patch.clone()) {
+                Err(Error::Patch { index: 0, .. }) => (),
+                other => panic!("{} should fail, got {:?}", patch, other),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_patches() {
+        for patch in &[
+            json!({"op": "add"}),
+            json!([{"op": "frobnicate", "path": "/a"}]),
+            json!([{"path": "/a"}]),
+            json!([{"op": "add", "path": "/a"}]),
+            json!([{"op": "move", "path": "/a"}]),
+            json!([{"op": "remove", "path": "a"}]),
+        ] {
+            assert!(Patch::from_value(patch).is_err(), "{}", patch);
+        }
+        match Patch::parse(r#"[{"op": "remove", "path": "/a"}, {"op": "nope"}]"#) {
+            Err(Error::Patch { index, .. }) => assert_eq!(index, 1),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_patches_roll_back() {
+        let original = json!({"meaningOfLife": 42, "list": [1, 2]});
+        let mut doc = original.clone();
+        let patch = Patch::from_value(&json!([
+            {"op": "replace", "path": "/meaningOfLife", "value": 43},
+            {"op": "add", "path": "/list/-", "value": 3},
+            {"op": "test", "path": "/meaningOfLife", "value": 42}
+        ]))
+        .unwrap();
+        match patch.apply(&mut doc) {
+            Err(Error::Patch { index, reason }) => {
+                assert_eq!(index, 2);
+                assert!(reason.contains("test failed"), "{}", reason);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(doc, original);
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let raw = json!([
+            {"op": "add", "path": "/a~1b", "value": [1]},
+            {"op": "remove", "path": "/c"},
+            {"op": "replace", "path": "", "value": null},
+            {"op": "move", "from": "/d", "path": "/e"},
+            {"op": "copy", "from": "/f", "path": "/g/0"},
+            {"op": "test", "path": "/h", "value": "i"}
+        ]);
+        assert_eq!(Patch::from_value(&raw).unwrap().to_value(), raw);
+    }
+
+    // the examples from RFC 7386 appendix A
+    #[test]
+    fn rfc7386_examples() {
+        let cases = vec![
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+        for (target, patch, expected) in cases {
+            let mut doc = target;
+            merge_patch(&mut doc, &patch);
+            assert_eq!(doc, expected, "{}", patch);
+        }
+    }
+
+    fn pairs() -> Vec<(Value, Value)> {
+        vec![
+            (json!({"meaningOfLife": 42}), json!({"meaningOfLife": 43})),
+            (json!({"a": 1, "b": 2}), json!({"b": 2, "c": {"d": [1]}})),
+            (json!([1, 2, 3, 4, 5]), json!([1, 3, 4, 6, 5])),
+            (json!([1, 2, 3]), json!([])),
+            (json!([]), json!([1, 2, 3])),
+            (
+                json!(["a", "b", "c", "d"]),
+                json!(["x", "a", "c", "d", "y"]),
+            ),
+            (
+                json!([{"id": 1, "v": "a"}, {"id": 2, "v": "b"}]),
+                json!([{"id": 1, "v": "z"}, {"id": 2, "v": "b"}, 3]),
+            ),
+            (
+                json!({"a": [1, {"b": null}]}),
+                json!({"a": [1, {"b": false}], "c": null}),
+            ),
+            (json!("scalar"), json!({"now": "object"})),
+            (json!({"same": [1, 2]}), json!({"same": [1, 2]})),
+        ]
+    }
+
+    #[test]
+    fn diff_produces_patches_that_apply() {
+        for (a, b) in pairs() {
+            let patch = diff(&a, &b);
+            let mut doc = a.clone();
+            patch
+                .apply(&mut doc)
+                .unwrap_or_else(|e| panic!("{} on {}: {}", patch, a, e));
+            assert_eq!(doc, b, "patch {} from {}", patch, a);
+
+            let mut merged = a.clone();
+            merge_patch(&mut merged, &merge_diff(&a, &b));
+            if !b.to_string().contains("null") {
+                assert_eq!(merged, b, "merge diff from {}", a);
+            }
+        }
+    }
+
+    #[test]
+    fn diff_is_minimal() {
+        assert!(diff(&json!({"same": [1, 2]}), &json!({"same": [1, 2]})).is_empty());
+        assert_eq!(
+            diff(
+                &json!({"meaningOfLife": 42, "keep": [1, 2, 3]}),
+                &json!({"meaningOfLife": 43, "keep": [1, 2, 3]})
+            )
+            .to_value(),
+            json!([{"op": "replace", "path": "/meaningOfLife", "value": 43}])
+        );
+        assert_eq!(
+            diff(&json!([1, 2, 3, 4]), &json!([1, 3, 4])).to_value(),
+            json!([{"op": "remove", "path": "/1"}])
+        );
+        assert_eq!(
+            diff(&json!(["a", "c"]), &json!(["a", "b", "c"])).to_value(),
+            json!([{"op": "add", "path": "/1", "value": "b"}])
+        );
+        assert_eq!(
+            diff(&json!([{"x": 1, "y": 2}]), &json!([{"x": 1, "y": 3}])).to_value(),
+            json!([{"op": "replace", "path": "/0/y", "value": 3}])
+        );
+    }
+
+    #[test]
+    fn diffs_long_arrays_by_index() {
+        let long = |range: ::std::ops::Range<i64>| Value::Array(range.map(Value::from).collect());
+        for (a, b) in [
+            (long(0..3000), long(1..3001)),
+            (long(0..3000), long(0..1500)),
+            (json!({"a": long(5..2005)}), json!({"a": long(0..3000)})),
+        ] {
+            let patch = diff(&a, &b);
+            let mut doc = a.clone();
+            patch.apply(&mut doc).unwrap();
+            assert_eq!(doc, b);
+        }
+        // a shift costs a replace per element, where the LCS would have found two operations
+        assert_eq!(diff(&long(0..3000), &long(1..3001)).0.len(), 3000);
+        assert_eq!(
+            diff(&long(0..3), &long(1..4)).to_value(),
+            json!([{"op": "remove", "path": "/0"}, {"op": "add", "path": "/2", "value": 3}])
+        );
+    }
+}
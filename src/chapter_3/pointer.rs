@@ -0,0 +1,347 @@
+// RFC 6901 JSON Pointers, eg. "/users/0/meaningOfLife"
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use chapter_3::{parse_input_to_json_value, type_name, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Pointer {
+    tokens: Vec<String>,
+}
+
+impl Pointer {
+    pub fn root() -> Pointer {
+        Pointer { tokens: Vec::new() }
+    }
+
+    pub fn parse(pointer: &str) -> Result<Pointer> {
+        if pointer.is_empty() {
+            return Ok(Pointer::root());
+        }
+        if !pointer.starts_with('/') {
+            return Err(invalid(pointer, "must be empty or start with '/'"));
+        }
+        let mut tokens = Vec::new();
+        for raw in pointer[1..].split('/') {
+            tokens.push(
+                unescape(raw)
+                    .ok_or_else(|| invalid(pointer, "'~' must be followed by '0' or '1'"))?,
+            );
+        }
+        Ok(Pointer { tokens })
+    }
+
+    // the unescaped reference tokens, in order
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn push<T: Into<String>>(&mut self, token: T) {
+        self.tokens.push(token.into());
+    }
+
+    pub fn child<T: Into<String>>(&self, token: T) -> Pointer {
+        let mut child = self.clone();
+        child.push(token);
+        child
+    }
+
+    // None for the root, otherwise the parent pointer and the final token
+    pub fn split_last(&self) -> Option<(Pointer, &str)> {
+        self.tokens.split_last().map(|(last, parent)| {
+            (
+                Pointer {
+                    tokens: parent.to_vec(),
+                },
+                last.as_str(),
+            )
+        })
+    }
+
+    pub fn starts_with(&self, other: &Pointer) -> bool {
+        self.tokens.starts_with(&other.tokens)
+    }
+
+    // walks the value one token at a time so an error names the exact segment that failed
+    pub fn resolve<'a>(&self, value: &'a Value) -> Result<&'a Value> {
+        let mut current = value;
+        let mut path = Pointer::root();
+        for token in &self.tokens {
+            let parent = path.to_string();
+            path.push(token.as_str());
+            current = match *current {
+                Value::Object(ref map) => map.get(token),
+                // "x" or "01" can't name an element any more than "5" can in a short array
+                Value::Array(ref items) => array_index(token).and_then(|i| items.get(i)),
+                ref other => {
+                    return Err(Error::WrongType {
+                        path: parent,
+                        expected: "object or array",
+                        found: type_name(other),
+                    })
+                }
+            }
+            .ok_or_else(|| Error::MissingKey {
+                path: path.to_string(),
+            })?;
+        }
+        Ok(current)
+    }
+
+    pub fn resolve_mut<'a>(&self, value: &'a mut Value) -> Result<&'a mut Value> {
+        // resolve immutably first for the error reporting, then walk again mutably
+        self.resolve(value)?;
+        let mut current = value;
+        for token in &self.tokens {
+            current = match *current {
+                Value::Object(ref mut map) => map.get_mut(token),
+                Value::Array(ref mut items) => {
+                    array_index(token).and_then(move |i| items.get_mut(i))
+                }
+                _ => None,
+            }
+            .expect("pointer was already resolved");
+        }
+        Ok(current)
+    }
+}
+
+impl fmt::Display for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Pointer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Pointer> {
+        Pointer::parse(s)
+    }
+}
+
+// array indexes are plain decimal with no leading zeros, so "01" and "+1" don't count
+pub fn array_index(token: &str) -> Option<usize> {
+    let digits_only = !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit());
+    if !digits_only || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn unescape(raw: &str) -> Option<String> {
+    let mut token = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' {
+            match chars.next() {
+                Some('0') => token.push('~'),
+                Some('1') => token.push('/'),
+                _ => return None,
+            }
+        } else {
+            token.push(c);
+        }
+    }
+    Some(token)
+}
+
+fn invalid(pointer: &str, reason: &'static str) -> Error {
+    Error::InvalidPointer {
+        pointer: pointer.to_string(),
+        reason,
+    }
+}
+
+// conversion from a JSON value into a Rust type, reporting the path on failure
+pub trait FromJson: Sized {
+    fn from_json(value: &Value, path: &str) -> Result<Self>;
+
+    // what to produce when nothing lives at the path; only Option is forgiving
+    fn from_missing(path: &str) -> Result<Self> {
+        Err(Error::MissingKey {
+            path: path.to_string(),
+        })
+    }
+}
+
+fn wrong_type(path: &str, expected: &'static str, value: &Value) -> Error {
+    Error::WrongType {
+        path: path.to_string(),
+        expected,
+        found: type_name(value),
+    }
+}
+
+impl FromJson for i64 {
+    fn from_json(value: &Value, path: &str) -> Result<i64> {
+        value.as_i64().ok_or_else(|| wrong_type(path, "i64", value))
+    }
+}
+
+impl FromJson for u64 {
+    fn from_json(value: &Value, path: &str) -> Result<u64> {
+        value.as_u64().ok_or_else(|| wrong_type(path, "u64", value))
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &Value, path: &str) -> Result<f64> {
+        value.as_f64().ok_or_else(|| wrong_type(path, "f64", value))
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &Value, path: &str) -> Result<bool> {
+        value
+            .as_bool()
+            .ok_or_else(|| wrong_type(path, "boolean", value))
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &Value, path: &str) -> Result<String> {
+        value
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| wrong_type(path, "string", value))
+    }
+}
+
+impl FromJson for Value {
+    fn from_json(value: &Value, _path: &str) -> Result<Value> {
+        Ok(value.clone())
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &Value, path: &str) -> Result<Vec<T>> {
+        let items = value
+            .as_array()
+            .ok_or_else(|| wrong_type(path, "array", value))?;
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_json(item, &format!("{}/{}", path, i)))
+            .collect()
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &Value, path: &str) -> Result<Option<T>> {
+        if value.is_null() {
+            Ok(None)
+        } else {
+            T::from_json(value, path).map(Some)
+        }
+    }
+
+    fn from_missing(_path: &str) -> Result<Option<T>> {
+        Ok(None)
+    }
+}
+
+pub fn extract_from<T: FromJson>(json: &Value, pointer: &str) -> Result<T> {
+    let pointer = Pointer::parse(pointer)?;
+    match pointer.resolve(json) {
+        Ok(value) => T::from_json(value, &pointer.to_string()),
+        Err(Error::MissingKey { path }) => T::from_missing(&path),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn extract<T: FromJson>(input: &str, pointer: &str) -> Result<T> {
+    let json = parse_input_to_json_value(input)?;
+    extract_from(&json, pointer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"{
+        "a": {"b": [{"c": 1}, {"c": 2}]},
+        "a/b": "slash",
+        "m~n": "tilde",
+        "": "empty",
+        "flags": [true, false],
+        "big": 18446744073709551615,
+        "nothing": null
+    }"#;
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for p in &["", "/", "/a/b/0/c", "/a~1b", "/m~0n", "/~01"] {
+            assert_eq!(Pointer::parse(p).unwrap().to_string(), *p);
+        }
+        assert_eq!(
+            Pointer::parse("/a~1b").unwrap().tokens(),
+            &["a/b".to_string()]
+        );
+        assert!(Pointer::parse("a/b").is_err());
+        assert!(Pointer::parse("/a~2").is_err());
+        assert!(Pointer::parse("/a~").is_err());
+    }
+
+    #[test]
+    fn extracts_typed_values() {
+        assert_eq!(extract::<i64>(DOC, "/a/b/1/c").unwrap(), 2);
+        assert_eq!(extract::<String>(DOC, "/a~1b").unwrap(), "slash");
+        assert_eq!(extract::<String>(DOC, "/m~0n").unwrap(), "tilde");
+        assert_eq!(extract::<String>(DOC, "/").unwrap(), "empty");
+        assert_eq!(
+            extract::<Vec<bool>>(DOC, "/flags").unwrap(),
+            vec![true, false]
+        );
+        assert_eq!(extract::<u64>(DOC, "/big").unwrap(), u64::MAX);
+        assert_eq!(extract::<f64>(DOC, "/a/b/0/c").unwrap(), 1.0);
+        assert_eq!(extract::<Value>(DOC, "/a/b/0").unwrap(), json!({"c": 1}));
+    }
+
+    #[test]
+    fn options_absorb_missing_and_null() {
+        assert_eq!(extract::<Option<i64>>(DOC, "/nothing").unwrap(), None);
+        assert_eq!(extract::<Option<i64>>(DOC, "/a/b/9/c").unwrap(), None);
+        assert_eq!(extract::<Option<i64>>(DOC, "/a/b/0/c").unwrap(), Some(1));
+        assert!(extract::<Option<i64>>(DOC, "/a~1b").is_err());
+    }
+
+    #[test]
+    fn reports_the_failing_segment() {
+        match extract::<i64>(DOC, "/a/b/5/c") {
+            Err(Error::MissingKey { path }) => assert_eq!(path, "/a/b/5"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match extract::<i64>(DOC, "/a/b/0/c/d") {
+            Err(Error::WrongType { path, found, .. }) => {
+                assert_eq!(path, "/a/b/0/c");
+                assert_eq!(found, "integer");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        for pointer in ["/a/b/01", "/a/b/x", "/a/b/-"] {
+            match extract::<i64>(DOC, pointer) {
+                Err(Error::MissingKey { path }) => assert_eq!(path, pointer),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        match extract::<Vec<i64>>(DOC, "/flags") {
+            Err(Error::WrongType { path, .. }) => assert_eq!(path, "/flags/0"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match extract::<i64>(DOC, "a") {
+            Err(Error::InvalidPointer { pointer, .. }) => assert_eq!(pointer, "a"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,336 @@
+// the output side of chapter_3: a configurable pretty-printer and a minifier, so fixtures
+// come out byte-for-byte the same no matter who formatted them
+use serde_json::Value;
+
+use chapter_3::{parse_input_to_json_value, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Printer {
+    indent: usize,
+    sort_keys: bool,
+    compact_arrays: usize,
+    compact_array_width: Option<usize>,
+    ascii_only: bool,
+}
+
+// the defaults print exactly what serde_json::to_string_pretty does
+impl Default for Printer {
+    fn default() -> Printer {
+        Printer {
+            indent: 2,
+            sort_keys: true,
+            compact_arrays: 0,
+            compact_array_width: None,
+            ascii_only: false,
+        }
+    }
+}
+
+impl Printer {
+    pub fn new() -> Printer {
+        Printer::default()
+    }
+
+    // spaces per nesting level
+    pub fn indent(mut self, width: usize) -> Printer {
+        self.indent = width;
+        self
+    }
+
+    // serde_json's Map is already sorted unless preserve_order is on; turning this off
+    // keeps whatever order the map iterates in
+    pub fn sort_keys(mut self, sort: bool) -> Printer {
+        self.sort_keys = sort;
+        self
+    }
+
+    // arrays of fewer than `items` scalars go on one line, eg. [1, 2, 3]
+    pub fn compact_arrays(mut self, items: usize) -> Printer {
+        self.compact_arrays = items;
+        self
+    }
+
+    // compact arrays that would run past this column are wrapped, filling each line; it only
+    // applies to them, since objects and other arrays already put one entry on each line
+    pub fn compact_array_width(mut self, width: usize) -> Printer {
+        self.compact_array_width = Some(width);
+        self
+    }
+
+    // escape everything outside ASCII as \uXXXX (surrogate pairs above the BMP)
+    pub fn ascii_only(mut self, ascii: bool) -> Printer {
+        self.ascii_only = ascii;
+        self
+    }
+
+    pub fn print(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write_value(value, 0, &mut out);
+        out
+    }
+
+    // reformats text, with the trailing newline a committed file should have
+    pub fn format_input(&self, input: &str) -> Result<String> {
+        let mut out = self.print(&parse_input_to_json_value(input)?);
+        out.push('\n');
+        Ok(out)
+    }
+
+    pub fn minify(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write_inline(value, ",", ":", &mut out);
+        out
+    }
+
+    fn write_value(&self, value: &Value, depth: usize, out: &mut String) {
+        match *value {
+            Value::Array(ref items) if !items.is_empty() => self.write_array(items, depth, out),
+            Value::Object(ref map) if !map.is_empty() => {
+                let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+                if self.sort_keys {
+                    entries.sort_by(|a, b| a.0.cmp(b.0));
+                }
+                out.push('{');
+                for (i, (key, item)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    self.newline(depth + 1, out);
+                    self.write_string(key, out);
+                    out.push_str(": ");
+                    self.write_value(item, depth + 1, out);
+                }
+                self.newline(depth, out);
+                out.push('}');
+            }
+            _ => self.write_inline(value, ",", ":", out),
+        }
+    }
+
+    fn write_array(&self, items: &[Value], depth: usize, out: &mut String) {
+        if items.len() < self.compact_arrays && items.iter().all(is_scalar) {
+            let parts: Vec<String> = items.iter().map(|item| self.minify(item)).collect();
+            let inline = format!("[{}]", parts.join(", "));
+            match self.compact_array_width {
+                Some(width) if column(out) + inline.chars().count() > width => {
+                    self.write_filled(&parts, depth, width, out)
+                }
+                _ => out.push_str(&inline),
+            }
+            return;
+        }
+        out.push('[');
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            self.newline(depth + 1, out);
+            self.write_value(item, depth + 1, out);
+        }
+        self.newline(depth, out);
+        out.push(']');
+    }
+
+    // as many items per line as fit; an item wider than the line still gets a line to itself
+    fn write_filled(&self, parts: &[String], depth: usize, width: usize, out: &mut String) {
+        out.push('[');
+        self.newline(depth + 1, out);
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+                if column(out) + 1 + part.chars().count() + 1 > width {
+                    self.newline(depth + 1, out);
+                } else {
+                    out.push(' ');
+                }
+            }
+            out.push_str(part);
+        }
+        self.newline(depth, out);
+        out.push(']');
+    }
+
+    fn write_inline(&self, value: &Value, comma: &str, colon: &str, out: &mut String) {
+        match *value {
+            Value::Null => out.push_str("null"),
+            Value::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+            Value::Number(ref n) => out.push_str(&n.to_string()),
+            Value::String(ref s) => self.write_string(s, out),
+            Value::Array(ref items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(comma);
+                    }
+                    self.write_inline(item, comma, colon, out);
+                }
+                out.push(']');
+            }
+            Value::Object(ref map) => {
+                let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+                if self.sort_keys {
+                    entries.sort_by(|a, b| a.0.cmp(b.0));
+                }
+                out.push('{');
+                for (i, (key, item)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(comma);
+                    }
+                    self.write_string(key, out);
+                    out.push_str(colon);
+                    self.write_inline(item, comma, colon, out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    fn write_string(&self, s: &str, out: &mut String) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\u{8}' => out.push_str("\\b"),
+                '\t' => out.push_str("\\t"),
+                '\n' => out.push_str("\\n"),
+                '\u{c}' => out.push_str("\\f"),
+                '\r' => out.push_str("\\r"),
+                c if c < ' ' || (self.ascii_only && !c.is_ascii()) => {
+                    let mut units = [0; 2];
+                    for unit in c.encode_utf16(&mut units) {
+                        out.push_str(&format!("\\u{:04x}", unit));
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    fn newline(&self, depth: usize, out: &mut String) {
+        out.push('\n');
+        out.push_str(&" ".repeat(depth * self.indent));
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(*value, Value::Array(_) | Value::Object(_))
+}
+
+fn column(out: &str) -> usize {
+    out[out.rfind('\n').map_or(0, |i| i + 1)..].chars().count()
+}
+
+pub fn pretty(value: &Value) -> String {
+    Printer::new().print(value)
+}
+
+pub fn minify(value: &Value) -> String {
+    Printer::new().minify(value)
+}
+
+pub fn minify_input(input: &str) -> Result<String> {
+    Ok(minify(&parse_input_to_json_value(input)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn sample() -> Value {
+        json!({
+            "meaningOfLife": 42,
+            "tags": ["towel", "fish"],
+            "author": {"name": "Douglas", "books": []},
+            "ratio": 0.5,
+            "empty": {},
+            "nested": [[1, 2], {"a": null}]
+        })
+    }
+
+    #[test]
+    fn defaults_match_serde_json() {
+        let value = sample();
+        assert_eq!(
+            pretty(&value),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+        assert_eq!(minify(&value), serde_json::to_string(&value).unwrap());
+        assert_eq!(pretty(&json!("a\u{1}\"\\\n")), "\"a\\u0001\\\"\\\\\\n\"");
+    }
+
+    #[test]
+    fn changes_the_indent_and_compacts_small_arrays() {
+        let printer = Printer::new().indent(4).compact_arrays(3);
+        assert_eq!(
+            printer.print(&json!({"tags": ["towel", "fish"], "ids": [1, 2, 3], "nested": [[1]]})),
+            "{\n    \"ids\": [\n        1,\n        2,\n        3\n    ],\n    \"nested\": [\n        [1]\n    ],\n    \"tags\": [\"towel\", \"fish\"]\n}"
+        );
+        assert_eq!(Printer::new().indent(0).print(&json!([1])), "[\n1\n]");
+    }
+
+    #[test]
+    fn wraps_compact_arrays_at_their_width() {
+        let printer = Printer::new().compact_arrays(100).compact_array_width(20);
+        let value = json!({"n": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]});
+        let printed = printer.print(&value);
+        assert_eq!(
+            printed,
+            "{\n  \"n\": [\n    1, 2, 3, 4, 5,\n    6, 7, 8, 9, 10,\n    11, 12\n  ]\n}"
+        );
+        assert!(
+            printed.lines().all(|l| l.chars().count() <= 20),
+            "{}",
+            printed
+        );
+        assert_eq!(
+            printer.print(&json!({"n": [1, 2]})),
+            "{\n  \"n\": [1, 2]\n}"
+        );
+        assert_eq!(
+            parse_input_to_json_value(&printed).unwrap(),
+            value,
+            "wrapping must not change the value"
+        );
+    }
+
+    #[test]
+    fn escapes_to_ascii_when_asked() {
+        let value = json!({"sign": "€", "face": "\u{1f600}", "plain": "é"});
+        assert_eq!(
+            Printer::new().ascii_only(true).minify(&value),
+            r#"{"face":"\ud83d\ude00","plain":"\u00e9","sign":"\u20ac"}"#
+        );
+        assert_eq!(minify(&json!("€")), "\"€\"");
+        let printed = Printer::new().ascii_only(true).print(&value);
+        assert!(printed.is_ascii());
+        assert_eq!(parse_input_to_json_value(&printed).unwrap(), value);
+    }
+
+    #[test]
+    fn normalizes_fixtures() {
+        let messy = "{ \"tags\" :[\"towel\"],\n\n \"meaningOfLife\":42 }";
+        let tidy = Printer::new()
+            .compact_arrays(4)
+            .format_input(messy)
+            .unwrap();
+        assert_eq!(
+            tidy,
+            "{\n  \"meaningOfLife\": 42,\n  \"tags\": [\"towel\"]\n}\n"
+        );
+        assert_eq!(
+            Printer::new()
+                .compact_arrays(4)
+                .format_input(&tidy)
+                .unwrap(),
+            tidy
+        );
+        assert_eq!(
+            minify_input(messy).unwrap(),
+            r#"{"meaningOfLife":42,"tags":["towel"]}"#
+        );
+        assert!(minify_input("'asdf'").is_err());
+    }
+}
@@ -0,0 +1,569 @@
+// JSON Schema validation, covering the parts of draft 2020-12 we lean on:
+// type, properties, required, items, enum, minimum/maximum, pattern and $ref
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use regex::Regex;
+use serde_json::{Number, Value};
+
+use chapter_3::{compare_numbers, json_eq, parse_input_to_json_value, type_name, Pointer, Result};
+use error::Error;
+
+// a schema document that has been checked up front, so validating can't fail halfway through
+#[derive(Debug)]
+pub struct Schema {
+    root: Value,
+    patterns: HashMap<String, Regex>,
+}
+
+// one way the instance broke the schema; validation collects every one of these
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    // JSON Pointer to the offending part of the instance
+    pub instance_path: String,
+    // JSON Pointer to the keyword in the schema that failed
+    pub keyword_location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.instance_path.is_empty() {
+            "(root)"
+        } else {
+            &self.instance_path
+        };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+const TYPES: &[&str] = &[
+    "null", "boolean", "object", "array", "number", "string", "integer",
+];
+
+impl Schema {
+    pub fn new(root: Value) -> Result<Schema> {
+        let mut patterns = HashMap::new();
+        let mut checked_refs = HashSet::new();
+        check(
+            &root,
+            &root,
+            &Pointer::root(),
+            &mut patterns,
+            &mut checked_refs,
+        )?;
+        Ok(Schema { root, patterns })
+    }
+
+    pub fn parse(input: &str) -> Result<Schema> {
+        Schema::new(parse_input_to_json_value(input)?)
+    }
+
+    pub fn validate(&self, instance: &Value) -> ::std::result::Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        let mut validator = Validator {
+            schema: self,
+            active_refs: HashSet::new(),
+            violations: &mut violations,
+        };
+        validator.validate(&self.root, &Pointer::root(), instance, &Pointer::root());
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &Value) -> bool {
+        self.validate(instance).is_ok()
+    }
+}
+
+// parse untrusted input and only hand it back if it has the shape the schema promises
+pub fn parse_validated(input: &str, schema: &Schema) -> Result<Value> {
+    let json = parse_input_to_json_value(input)?;
+    schema.validate(&json).map_err(Error::Validation)?;
+    Ok(json)
+}
+
+fn invalid_schema(at: &Pointer, reason: String) -> Error {
+    Error::InvalidSchema {
+        path: at.to_string(),
+        reason,
+    }
+}
+
+// walks the whole schema once, compiling patterns and making sure every $ref resolves; a $ref
+// can point anywhere in the document, eg. an old-style "definitions", so its target is checked
+// too, once per distinct reference
+fn check(
+    root: &Value,
+    schema: &Value,
+    at: &Pointer,
+    patterns: &mut HashMap<String, Regex>,
+    checked_refs: &mut HashSet<String>,
+) -> Result<()> {
+    let map = match *schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(ref map) => map,
+        ref other => {
+            return Err(invalid_schema(
+                at,
+                format!(
+                    "a schema must be an object or boolean, found {}",
+                    type_name(other)
+                ),
+            ))
+        }
+    };
+    if let Some(types) = map.get("type") {
+        let names: Vec<&Value> = match *types {
+            Value::Array(ref names) => names.iter().collect(),
+            ref name => vec![name],
+        };
+        for name in names {
+            if !name.as_str().is_some_and(|n| TYPES.contains(&n)) {
+                return Err(invalid_schema(
+                    &at.child("type"),
+                    format!("unknown type {}", name),
+                ));
+            }
+        }
+    }
+    if let Some(pattern) = map.get("pattern") {
+        let source = pattern.as_str().ok_or_else(|| {
+            invalid_schema(&at.child("pattern"), "pattern must be a string".to_string())
+        })?;
+        let regex =
+            Regex::new(source).map_err(|e| invalid_schema(&at.child("pattern"), e.to_string()))?;
+        patterns.insert(source.to_string(), regex);
+    }
+    if let Some(reference) = map.get("$ref") {
+        let reference = reference.as_str().ok_or_else(|| {
+            invalid_schema(&at.child("$ref"), "$ref must be a string".to_string())
+        })?;
+        let target = resolve_ref(root, reference)
+            .map_err(|reason| invalid_schema(&at.child("$ref"), reason))?;
+        if checked_refs.insert(reference.to_string()) {
+            let target_at = Pointer::parse(&reference[1..])?;
+            check(root, target, &target_at, patterns, checked_refs)?;
+        }
+    }
+    if let Some(required) = map.get("required") {
+        let names_only = required
+            .as_array()
+            .is_some_and(|names| names.iter().all(Value::is_string));
+        if !names_only {
+            return Err(invalid_schema(
+                &at.child("required"),
+                "required must be an array of strings".to_string(),
+            ));
+        }
+    }
+    if map.get("enum").is_some_and(|e| !e.is_array()) {
+        return Err(invalid_schema(
+            &at.child("enum"),
+            "enum must be an array".to_string(),
+        ));
+    }
+    for keyword in &["minimum", "maximum"] {
+        if map.get(*keyword).is_some_and(|n| !n.is_number()) {
+            return Err(invalid_schema(
+                &at.child(*keyword),
+                format!("{} must be a number", keyword),
+            ));
+        }
+    }
+    if let Some(items) = map.get("items") {
+        check(root, items, &at.child("items"), patterns, checked_refs)?;
+    }
+    for keyword in &["properties", "$defs"] {
+        if let Some(subschemas) = map.get(*keyword) {
+            let subschemas = subschemas.as_object().ok_or_else(|| {
+                invalid_schema(
+                    &at.child(*keyword),
+                    format!("{} must be an object", keyword),
+                )
+            })?;
+            for (name, subschema) in subschemas {
+                check(
+                    root,
+                    subschema,
+                    &at.child(*keyword).child(name.as_str()),
+                    patterns,
+                    checked_refs,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// only references into the same document are supported, eg. "#" or "#/$defs/positive"
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> ::std::result::Result<&'a Value, String> {
+    if !reference.starts_with('#') {
+        return Err(format!(
+            "only local references are supported, found {:?}",
+            reference
+        ));
+    }
+    let pointer = Pointer::parse(&reference[1..]).map_err(|e| e.to_string())?;
+    pointer.resolve(root).map_err(|e| e.to_string())
+}
+
+struct Validator<'s, 'v> {
+    schema: &'s Schema,
+    // $refs currently being expanded against an instance location, to stop `{"$ref": "#"}` looping
+    active_refs: HashSet<(String, String)>,
+    violations: &'v mut Vec<Violation>,
+}
+
+impl<'s, 'v> Validator<'s, 'v> {
+    fn report(&mut self, at: &Pointer, keyword: &str, instance_path: &Pointer, message: String) {
+        self.violations.push(Violation {
+            instance_path: instance_path.to_string(),
+            keyword_location: at.child(keyword).to_string(),
+            message,
+        });
+    }
+
+    fn validate(&mut self, schema: &'s Value, at: &Pointer, instance: &Value, path: &Pointer) {
+        let map = match *schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                self.violations.push(Violation {
+                    instance_path: path.to_string(),
+                    keyword_location: at.to_string(),
+                    message: "no value is allowed here".to_string(),
+                });
+                return;
+            }
+            Value::Object(ref map) => map,
+            _ => return,
+        };
+
+        if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+            let key = (reference.to_string(), path.to_string());
+            if self.active_refs.insert(key.clone()) {
+                if let Ok(target) = resolve_ref(&self.schema.root, reference) {
+                    self.validate(target, &at.child("$ref"), instance, path);
+                }
+                self.active_refs.remove(&key);
+            }
+        }
+
+        if let Some(types) = map.get("type") {
+            let allowed: Vec<&str> = match *types {
+                Value::Array(ref names) => names.iter().filter_map(Value::as_str).collect(),
+                ref name => name.as_str().into_iter().collect(),
+            };
+            if !allowed.iter().any(|t| has_type(instance, t)) {
+                self.report(
+                    at,
+                    "type",
+                    path,
+                    format!(
+                        "expected {}, found {}",
+                        allowed.join(" or "),
+                        type_name(instance)
+                    ),
+                );
+            }
+        }
+
+        if let Some(options) = map.get("enum").and_then(Value::as_array) {
+            if !options.iter().any(|option| json_eq(option, instance)) {
+                self.report(
+                    at,
+                    "enum",
+                    path,
+                    format!(
+                        "{} is not one of {}",
+                        instance,
+                        Value::Array(options.clone())
+                    ),
+                );
+            }
+        }
+
+        if let Value::Number(ref n) = *instance {
+            if let Some(Value::Number(min)) = map.get("minimum") {
+                if compare_numbers(n, min).is_some_and(|o| o.is_lt()) {
+                    self.report(
+                        at,
+                        "minimum",
+                        path,
+                        format!("{} is less than the minimum of {}", n, min),
+                    );
+                }
+            }
+            if let Some(Value::Number(max)) = map.get("maximum") {
+                if compare_numbers(n, max).is_some_and(|o| o.is_gt()) {
+                    self.report(
+                        at,
+                        "maximum",
+                        path,
+                        format!("{} is greater than the maximum of {}", n, max),
+                    );
+                }
+            }
+        }
+
+        if let (Some(pattern), Value::String(ref s)) =
+            (map.get("pattern").and_then(Value::as_str), instance)
+        {
+            if !self.schema.patterns[pattern].is_match(s) {
+                self.report(
+                    at,
+                    "pattern",
+                    path,
+                    format!("{:?} does not match the pattern {:?}", s, pattern),
+                );
+            }
+        }
+
+        if let Value::Object(ref object) = *instance {
+            if let Some(required) = map.get("required").and_then(Value::as_array) {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        self.report(
+                            at,
+                            "required",
+                            path,
+                            format!("missing required property {:?}", name),
+                        );
+                    }
+                }
+            }
+            if let Some(properties) = map.get("properties").and_then(Value::as_object) {
+                let properties_at = at.child("properties");
+                for (name, subschema) in properties {
+                    if let Some(value) = object.get(name) {
+                        self.validate(
+                            subschema,
+                            &properties_at.child(name.as_str()),
+                            value,
+                            &path.child(name.as_str()),
+                        );
+                    }
+                }
+            }
+        }
+
+        if let (Some(items), Value::Array(ref elements)) = (map.get("items"), instance) {
+            let items_at = at.child("items");
+            for (i, element) in elements.iter().enumerate() {
+                self.validate(items, &items_at, element, &path.child(i.to_string()));
+            }
+        }
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match (name, instance) {
+        ("integer", Value::Number(n)) => is_integer(n),
+        ("number", Value::Number(_)) => true,
+        (name, instance) => type_name(instance) == name,
+    }
+}
+
+// 2020-12 counts 1.0 as an integer, so check the value rather than how it was written
+fn is_integer(n: &Number) -> bool {
+    n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(json: Value) -> Schema {
+        Schema::new(json).unwrap()
+    }
+
+    fn violations(schema: &Schema, instance: Value) -> Vec<(String, String)> {
+        match schema.validate(&instance) {
+            Ok(()) => Vec::new(),
+            Err(found) => found
+                .into_iter()
+                .map(|v| (v.instance_path, v.keyword_location))
+                .collect(),
+        }
+    }
+
+    fn meaning_schema() -> Schema {
+        schema(json!({
+            "type": "object",
+            "required": ["meaningOfLife"],
+            "properties": {
+                "meaningOfLife": {"type": "integer", "minimum": 0, "maximum": 100},
+                "author": {"type": "string", "pattern": "^[A-Z]"},
+                "mood": {"enum": ["calm", "panic", 42]},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+            },
+            "$defs": {
+                "tag": {"type": "string", "pattern": "^[a-z]+$"}
+            }
+        }))
+    }
+
+    #[test]
+    fn accepts_valid_documents() {
+        let schema = meaning_schema();
+        assert!(schema.is_valid(&json!({"meaningOfLife": 42})));
+        assert!(schema.is_valid(
+            &json!({"meaningOfLife": 42.0, "author": "Douglas", "mood": 42.0, "tags": ["towel"]})
+        ));
+    }
+
+    #[test]
+    fn reports_every_violation_with_its_path() {
+        let found = violations(
+            &meaning_schema(),
+            json!({"meaningOfLife": 420, "author": "douglas", "mood": "bored", "tags": ["ok", "Nope", 7]}),
+        );
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "/author".to_string(),
+                    "/properties/author/pattern".to_string()
+                ),
+                (
+                    "/meaningOfLife".to_string(),
+                    "/properties/meaningOfLife/maximum".to_string()
+                ),
+                ("/mood".to_string(), "/properties/mood/enum".to_string()),
+                (
+                    "/tags/1".to_string(),
+                    "/properties/tags/items/$ref/pattern".to_string()
+                ),
+                (
+                    "/tags/2".to_string(),
+                    "/properties/tags/items/$ref/type".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_mistyped_values() {
+        let schema = meaning_schema();
+        assert_eq!(
+            violations(&schema, json!({})),
+            vec![("".to_string(), "/required".to_string())]
+        );
+        assert_eq!(
+            violations(&schema, json!([])),
+            vec![("".to_string(), "/type".to_string())]
+        );
+        assert_eq!(
+            violations(&schema, json!({"meaningOfLife": 4.2})),
+            vec![(
+                "/meaningOfLife".to_string(),
+                "/properties/meaningOfLife/type".to_string()
+            )]
+        );
+        assert_eq!(
+            violations(&schema, json!({"meaningOfLife": -1})),
+            vec![(
+                "/meaningOfLife".to_string(),
+                "/properties/meaningOfLife/minimum".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn boolean_schemas_and_type_lists() {
+        assert!(schema(json!(true)).is_valid(&json!("anything")));
+        assert!(!schema(json!(false)).is_valid(&json!(null)));
+        let nullable = schema(json!({"type": ["string", "null"]}));
+        assert!(nullable.is_valid(&json!(null)));
+        assert!(nullable.is_valid(&json!("x")));
+        assert_eq!(
+            nullable.validate(&json!(1)).unwrap_err()[0].message,
+            "expected string or null, found integer"
+        );
+    }
+
+    #[test]
+    fn recursive_refs_terminate() {
+        let tree = schema(json!({
+            "type": "object",
+            "properties": {"children": {"type": "array", "items": {"$ref": "#"}}},
+            "required": ["name"]
+        }));
+        assert!(tree.is_valid(&json!({"name": "a", "children": [{"name": "b", "children": []}]})));
+        assert_eq!(
+            violations(&tree, json!({"name": "a", "children": [{"children": []}]})),
+            vec![(
+                "/children/0".to_string(),
+                "/properties/children/items/$ref/required".to_string()
+            )]
+        );
+        assert!(schema(json!({"$ref": "#"})).is_valid(&json!(1)));
+    }
+
+    #[test]
+    fn checks_ref_targets_outside_defs() {
+        let legacy = schema(json!({
+            "definitions": {"s": {"pattern": "^a"}},
+            "$ref": "#/definitions/s"
+        }));
+        assert!(legacy.is_valid(&json!("abc")));
+        assert_eq!(
+            violations(&legacy, json!("b")),
+            vec![("".to_string(), "/$ref/pattern".to_string())]
+        );
+        match Schema::new(
+            json!({"definitions": {"s": {"type": "float"}}, "$ref": "#/definitions/s"}),
+        ) {
+            Err(Error::InvalidSchema { path, .. }) => assert_eq!(path, "/definitions/s/type"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_broken_schemas() {
+        for broken in [
+            json!(42),
+            json!({"type": "float"}),
+            json!({"pattern": "("}),
+            json!({"$ref": "#/$defs/nope"}),
+            json!({"$ref": "http://example.com/schema"}),
+            json!({"properties": {"a": {"required": "a"}}}),
+            json!({"items": {"minimum": "0"}}),
+        ] {
+            match Schema::new(broken.clone()) {
+                Err(Error::InvalidSchema { .. }) => (),
+                other => panic!("{} should be rejected, got {:?}", broken, other),
+            }
+        }
+        match Schema::new(json!({"properties": {"a": {"type": "float"}}})) {
+            Err(Error::InvalidSchema { path, .. }) => assert_eq!(path, "/properties/a/type"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validates_before_extracting() {
+        let schema = meaning_schema();
+        let json = parse_validated(r#"{"meaningOfLife": 42}"#, &schema).unwrap();
+        assert_eq!(
+            ::chapter_3::extract_from::<i64>(&json, "/meaningOfLife").unwrap(),
+            42
+        );
+        match parse_validated(r#"{"meaningOfLife": "42"}"#, &schema) {
+            Err(Error::Validation(violations)) => {
+                assert_eq!(
+                    violations[0].to_string(),
+                    "/meaningOfLife: expected integer, found string"
+                )
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            parse_validated("'asdf'", &schema),
+            Err(Error::Parse(_))
+        ));
+    }
+}
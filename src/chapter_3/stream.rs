@@ -0,0 +1,850 @@
+// streaming JSON: read from any io::Read one token at a time instead of building the
+// whole Value tree, so multi-GB inputs only ever hold one element in memory
+use std::io::{self, BufRead, BufReader, Read};
+use std::str::FromStr;
+
+use serde_json::{Map, Number, Value};
+
+use chapter_3::limits::{BigNumbers, DuplicateKeys};
+use chapter_3::pointer::array_index;
+use chapter_3::{type_name, FromJson, ParseOptions, Pointer, Result};
+use error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    String(String),
+    // kept as written so nothing is lost before the caller decides how to read it
+    Number(String),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+// a container value_from is still filling, with the key waiting for its value
+enum Partial {
+    Array(Vec<Value>),
+    Object(Map<String, Value>, Option<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    // a value must come next (the start of input, after ':' or after ',' in an array)
+    Value,
+    // just after '['
+    ValueOrEnd,
+    // just after '{'
+    KeyOrEnd,
+    // after ',' in an object
+    Key,
+    // after a complete value inside a container
+    CommaOrEnd,
+    // the top-level value is complete; only whitespace may follow
+    Done,
+    // an error was returned, stop producing events
+    Failed,
+}
+
+pub struct EventReader<R> {
+    input: BufReader<R>,
+    line: usize,
+    column: usize,
+    stack: Vec<Container>,
+    // entries seen so far in each open container, alongside `stack`
+    entries: Vec<usize>,
+    consumed: usize,
+    state: State,
+    options: ParseOptions,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(input: R) -> EventReader<R> {
+        EventReader::with_options(input, ParseOptions::default())
+    }
+
+    // every limit in the options is checked as the input is read, not after
+    pub fn with_options(input: R, options: ParseOptions) -> EventReader<R> {
+        EventReader {
+            input: BufReader::new(input),
+            line: 1,
+            column: 1,
+            stack: Vec::new(),
+            entries: Vec::new(),
+            consumed: 0,
+            state: State::Value,
+            options,
+        }
+    }
+
+    // how many containers the last event left open
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Event>> {
+        if self.state == State::Failed {
+            return Ok(None);
+        }
+        let event = self.read_event();
+        if event.is_err() {
+            self.state = State::Failed;
+        }
+        event
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>> {
+        loop {
+            self.skip_whitespace()?;
+            let next = self.peek()?;
+            match self.state {
+                State::Failed => return Ok(None),
+                State::Done => {
+                    return match next {
+                        None => Ok(None),
+                        Some(_) => Err(self.syntax("trailing characters after the JSON value")),
+                    }
+                }
+                State::Value => return self.value().map(Some),
+                State::ValueOrEnd if next == Some(b']') => return self.end(Container::Array),
+                State::ValueOrEnd => return self.value().map(Some),
+                State::KeyOrEnd if next == Some(b'}') => return self.end(Container::Object),
+                State::KeyOrEnd | State::Key => return self.key().map(Some),
+                State::CommaOrEnd => {
+                    let container = *self.stack.last().expect("inside a container");
+                    match (next, container) {
+                        (Some(b','), Container::Object) => {
+                            self.bump()?;
+                            self.state = State::Key;
+                        }
+                        (Some(b','), Container::Array) => {
+                            self.bump()?;
+                            self.state = State::Value;
+                        }
+                        (Some(b'}'), Container::Object) => return self.end(container),
+                        (Some(b']'), Container::Array) => return self.end(container),
+                        (None, _) => return Err(self.syntax("unexpected end of input")),
+                        (Some(_), Container::Object) => {
+                            return Err(self.syntax("expected ',' or '}'"))
+                        }
+                        (Some(_), Container::Array) => {
+                            return Err(self.syntax("expected ',' or ']'"))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn end(&mut self, container: Container) -> Result<Option<Event>> {
+        self.bump()?;
+        self.stack.pop();
+        self.entries.pop();
+        self.after_value();
+        Ok(Some(match container {
+            Container::Object => Event::EndObject,
+            Container::Array => Event::EndArray,
+        }))
+    }
+
+    fn after_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::CommaOrEnd
+        };
+    }
+
+    fn key(&mut self) -> Result<Event> {
+        self.count_entry()?;
+        if self.peek()? != Some(b'"') {
+            return Err(self.syntax("expected a string key"));
+        }
+        let key = self.string()?;
+        self.skip_whitespace()?;
+        if self.peek()? != Some(b':') {
+            return Err(self.syntax("expected ':' after an object key"));
+        }
+        self.bump()?;
+        self.state = State::Value;
+        Ok(Event::Key(key))
+    }
+
+    fn value(&mut self) -> Result<Event> {
+        if self.stack.last() == Some(&Container::Array) {
+            self.count_entry()?;
+        }
+        let event = match self.peek()? {
+            Some(b'{') => {
+                self.open(Container::Object)?;
+                self.state = State::KeyOrEnd;
+                return Ok(Event::StartObject);
+            }
+            Some(b'[') => {
+                self.open(Container::Array)?;
+                self.state = State::ValueOrEnd;
+                return Ok(Event::StartArray);
+            }
+            Some(b'"') => Event::String(self.string()?),
+            Some(b't') => self.literal("true", Event::Bool(true))?,
+            Some(b'f') => self.literal("false", Event::Bool(false))?,
+            Some(b'n') => self.literal("null", Event::Null)?,
+            Some(b) if b == b'-' || b.is_ascii_digit() => Event::Number(self.number()?),
+            Some(_) => return Err(self.syntax("expected a JSON value")),
+            None => return Err(self.syntax("unexpected end of input")),
+        };
+        self.after_value();
+        Ok(event)
+    }
+
+    fn open(&mut self, container: Container) -> Result<()> {
+        if self.stack.len() >= self.options.max_depth {
+            return Err(self.limit(&format!(
+                "nesting deeper than {} levels",
+                self.options.max_depth
+            )));
+        }
+        self.bump()?;
+        self.stack.push(container);
+        self.entries.push(0);
+        Ok(())
+    }
+
+    fn count_entry(&mut self) -> Result<()> {
+        let max = self.options.max_entries;
+        let count = self.entries.last_mut().expect("inside a container");
+        *count += 1;
+        if *count > max {
+            return Err(self.limit(&format!("more than {} entries in one container", max)));
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, event: Event) -> Result<Event> {
+        for expected in word.bytes() {
+            if self.peek()? != Some(expected) {
+                return Err(self.syntax(&format!("expected '{}'", word)));
+            }
+            self.bump()?;
+        }
+        Ok(event)
+    }
+
+    fn number(&mut self) -> Result<String> {
+        let mut raw = String::new();
+        while let Some(b) = self.peek()? {
+            if b.is_ascii_digit() || b == b'-' || b == b'+' || b == b'.' || b == b'e' || b == b'E' {
+                raw.push(b as char);
+                self.bump()?;
+                // a number's digits count against the string limit too
+                if raw.len() > self.options.max_string_length {
+                    return Err(self.limit(&format!(
+                        "number longer than {} bytes",
+                        self.options.max_string_length
+                    )));
+                }
+            } else {
+                break;
+            }
+        }
+        if !is_json_number(&raw) {
+            return Err(self.syntax(&format!("invalid number {:?}", raw)));
+        }
+        Ok(raw)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.bump()?; // opening quote
+        let mut bytes = Vec::new();
+        loop {
+            match self.bump()? {
+                None => return Err(self.syntax("unterminated string")),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let escaped = match self.bump()? {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.unicode_escape()?,
+                        _ => return Err(self.syntax("invalid escape")),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                Some(b) if b < 0x20 => return Err(self.syntax("control character in string")),
+                Some(b) => bytes.push(b),
+            }
+            if bytes.len() > self.options.max_string_length {
+                return Err(self.limit(&format!(
+                    "string longer than {} bytes",
+                    self.options.max_string_length
+                )));
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.syntax("invalid UTF-8 in string"))
+    }
+
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.bump()? != Some(b'\\') || self.bump()? != Some(b'u') {
+                return Err(self.syntax("unpaired surrogate"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.syntax("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        ::std::char::from_u32(code).ok_or_else(|| self.syntax("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .bump()?
+                .and_then(|b| (b as char).to_digit(16))
+                .ok_or_else(|| self.syntax("expected four hex digits"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        while let Some(b) = self.peek()? {
+            if b == b' ' || b == b'\t' || b == b'\n' || b == b'\r' {
+                self.bump()?;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        loop {
+            match self.input.fill_buf() {
+                Ok(buf) => return Ok(buf.first().cloned()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+    }
+
+    fn bump(&mut self) -> Result<Option<u8>> {
+        let next = self.peek()?;
+        if let Some(b) = next {
+            self.input.consume(1);
+            self.consumed += 1;
+            if b == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+            if self.consumed > self.options.max_input_bytes {
+                return Err(self.limit(&format!(
+                    "input larger than {} bytes",
+                    self.options.max_input_bytes
+                )));
+            }
+        }
+        Ok(next)
+    }
+
+    fn syntax(&self, reason: &str) -> Error {
+        Error::Syntax {
+            line: self.line,
+            column: self.column,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn limit(&self, reason: &str) -> Error {
+        Error::LimitExceeded {
+            line: self.line,
+            column: self.column,
+            reason: reason.to_string(),
+        }
+    }
+
+    // builds the value that `first` starts, consuming the events that belong to it; the open
+    // containers live on a Vec rather than the call stack so depth is bounded only by max_depth
+    pub fn value_from(&mut self, first: Event) -> Result<Value> {
+        let mut open: Vec<Partial> = Vec::new();
+        let mut next = Some(first);
+        loop {
+            let event = match next.take() {
+                Some(event) => event,
+                None => self.require_event()?,
+            };
+            let value = match event {
+                Event::Null => Value::Null,
+                Event::Bool(b) => Value::Bool(b),
+                Event::Number(raw) => self.number_value(raw)?,
+                Event::String(s) => Value::String(s),
+                Event::StartArray => {
+                    open.push(Partial::Array(Vec::new()));
+                    continue;
+                }
+                Event::StartObject => {
+                    open.push(Partial::Object(Map::new(), None));
+                    continue;
+                }
+                Event::Key(key) => {
+                    if let Some(&mut Partial::Object(ref map, ref mut pending)) = open.last_mut() {
+                        if map.contains_key(&key) {
+                            match self.options.duplicate_keys {
+                                DuplicateKeys::Error => {
+                                    return Err(self.limit(&format!("duplicate key {:?}", key)))
+                                }
+                                DuplicateKeys::First => {
+                                    let skipped = self.require_event()?;
+                                    self.skip_from(&skipped)?;
+                                    continue;
+                                }
+                                DuplicateKeys::Last => (),
+                            }
+                        }
+                        *pending = Some(key);
+                        continue;
+                    }
+                    unreachable!("the reader only yields keys inside objects")
+                }
+                Event::EndArray | Event::EndObject => match open.pop() {
+                    Some(Partial::Array(items)) => Value::Array(items),
+                    Some(Partial::Object(map, _)) => Value::Object(map),
+                    None => unreachable!("value_from called in the middle of a container"),
+                },
+            };
+            match open.last_mut() {
+                None => return Ok(value),
+                Some(&mut Partial::Array(ref mut items)) => items.push(value),
+                Some(&mut Partial::Object(ref mut map, ref mut pending)) => {
+                    map.insert(pending.take().expect("a key precedes every value"), value);
+                }
+            }
+        }
+    }
+
+    // "big" means an integer outside i64 and u64, or anything outside f64's range
+    fn number_value(&self, raw: String) -> Result<Value> {
+        let fits = if raw.contains(['.', 'e', 'E']) {
+            to_number(&raw).is_ok()
+        } else {
+            raw.parse::<i64>().is_ok() || raw.parse::<u64>().is_ok()
+        };
+        match self.options.big_numbers {
+            _ if fits => Ok(Value::Number(to_number(&raw)?)),
+            BigNumbers::Lossy => Ok(Value::Number(to_number(&raw)?)),
+            BigNumbers::String => Ok(Value::String(raw)),
+            BigNumbers::Reject => Err(self.limit(&format!("number {} is out of range", raw))),
+        }
+    }
+
+    // reads the next complete value, or None once the input is exhausted
+    pub fn next_value(&mut self) -> Result<Option<Value>> {
+        match self.next_event()? {
+            None => Ok(None),
+            Some(event) => self.value_from(event).map(Some),
+        }
+    }
+
+    // consumes the rest of the value that `first` starts without keeping any of it
+    pub fn skip_from(&mut self, first: &Event) -> Result<()> {
+        if *first != Event::StartObject && *first != Event::StartArray {
+            return Ok(());
+        }
+        let depth = self.depth();
+        while self.depth() >= depth {
+            self.require_event()?;
+        }
+        Ok(())
+    }
+
+    fn require_event(&mut self) -> Result<Event> {
+        match self.next_event()? {
+            Some(event) => Ok(event),
+            None => Err(self.syntax("unexpected end of input")),
+        }
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        self.next_event().transpose()
+    }
+}
+
+pub(crate) fn is_json_number(raw: &str) -> bool {
+    let bytes = raw.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match bytes.get(i) {
+        Some(&b'0') => i += 1,
+        Some(b) if b.is_ascii_digit() => {
+            digits(&mut i);
+        }
+        _ => return false,
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if bytes.get(i) == Some(&b'e') || bytes.get(i) == Some(&b'E') {
+        i += 1;
+        if bytes.get(i) == Some(&b'+') || bytes.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
+// the JSON type a value starting with this event will have
+pub(crate) fn event_type(event: &Event) -> &'static str {
+    match *event {
+        Event::StartObject => "object",
+        Event::StartArray => "array",
+        Event::String(_) => "string",
+        // the same split as type_name, so both readers describe a document alike
+        Event::Number(ref raw) => to_number(raw).map_or("number", |n| type_name(&Value::Number(n))),
+        Event::Bool(_) => "boolean",
+        Event::Null => "null",
+        Event::Key(_) | Event::EndObject | Event::EndArray => "end of container",
+    }
+}
+
+fn to_number(raw: &str) -> Result<Number> {
+    Number::from_str(raw).map_err(Error::Parse)
+}
+
+// yields the elements of a top-level array one at a time
+pub struct ArrayElements<R> {
+    events: EventReader<R>,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: Read> ArrayElements<R> {
+    fn next_element(&mut self) -> Result<Option<Value>> {
+        if !self.started {
+            self.started = true;
+            match self.events.require_event()? {
+                Event::StartArray => (),
+                other => {
+                    return Err(Error::WrongType {
+                        path: String::new(),
+                        expected: "array",
+                        found: event_type(&other),
+                    })
+                }
+            }
+        }
+        match self.events.require_event()? {
+            Event::EndArray => {
+                // make sure nothing but whitespace follows the array
+                self.events.next_event()?;
+                Ok(None)
+            }
+            event => self.events.value_from(event).map(Some),
+        }
+    }
+}
+
+impl<R: Read> Iterator for ArrayElements<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Result<Value>> {
+        if self.finished {
+            return None;
+        }
+        let element = self.next_element();
+        if !matches!(element, Ok(Some(_))) {
+            self.finished = true;
+        }
+        element.transpose()
+    }
+}
+
+pub fn array_elements<R: Read>(input: R) -> ArrayElements<R> {
+    ArrayElements {
+        events: EventReader::new(input),
+        started: false,
+        finished: false,
+    }
+}
+
+// walks down to the pointer, skipping every sibling on the way, and builds only the target
+pub fn find<R: Read>(input: R, pointer: &Pointer) -> Result<Option<Value>> {
+    let mut events = EventReader::new(input);
+    match seek(&mut events, pointer)? {
+        Some(first) => events.value_from(first).map(Some),
+        None => Ok(None),
+    }
+}
+
+// the event that starts the value at the pointer, with the reader positioned just after it
+pub fn seek<R: Read>(events: &mut EventReader<R>, pointer: &Pointer) -> Result<Option<Event>> {
+    let mut current = events.require_event()?;
+    for token in pointer.tokens() {
+        let found = match current {
+            Event::StartObject => seek_key(events, token)?,
+            Event::StartArray => match array_index(token) {
+                Some(index) => seek_index(events, index)?,
+                None => None,
+            },
+            _ => None,
+        };
+        current = match found {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(current))
+}
+
+fn seek_key<R: Read>(events: &mut EventReader<R>, wanted: &str) -> Result<Option<Event>> {
+    loop {
+        match events.require_event()? {
+            Event::EndObject => return Ok(None),
+            Event::Key(ref key) if key == wanted => return events.require_event().map(Some),
+            Event::Key(_) => {
+                let skipped = events.require_event()?;
+                events.skip_from(&skipped)?;
+            }
+            _ => unreachable!("the reader only yields keys inside objects"),
+        }
+    }
+}
+
+fn seek_index<R: Read>(events: &mut EventReader<R>, wanted: usize) -> Result<Option<Event>> {
+    let mut index = 0;
+    loop {
+        match events.require_event()? {
+            Event::EndArray => return Ok(None),
+            event if index == wanted => return Ok(Some(event)),
+            skipped => events.skip_from(&skipped)?,
+        }
+        index += 1;
+    }
+}
+
+pub fn extract_from_reader<R: Read, T: FromJson>(input: R, pointer: &str) -> Result<T> {
+    let pointer = Pointer::parse(pointer)?;
+    match find(input, &pointer)? {
+        Some(value) => T::from_json(&value, &pointer.to_string()),
+        None => T::from_missing(&pointer.to_string()),
+    }
+}
+
+pub fn get_meaning_of_life_from_reader<R: Read>(input: R) -> Result<i64> {
+    extract_from_reader(input, "/meaningOfLife")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn events(input: &str) -> Result<Vec<Event>> {
+        EventReader::new(input.as_bytes()).collect()
+    }
+
+    #[test]
+    fn emits_events_in_order() {
+        assert_eq!(
+            events(r#" {"a": [1, -2.5e3, "x\n\u00e9\ud83d\ude00"], "b": {}, "c": [true, false, null]} "#).unwrap(),
+            vec![
+                Event::StartObject,
+                Event::Key("a".to_string()),
+                Event::StartArray,
+                Event::Number("1".to_string()),
+                Event::Number("-2.5e3".to_string()),
+                Event::String("x\né😀".to_string()),
+                Event::EndArray,
+                Event::Key("b".to_string()),
+                Event::StartObject,
+                Event::EndObject,
+                Event::Key("c".to_string()),
+                Event::StartArray,
+                Event::Bool(true),
+                Event::Bool(false),
+                Event::Null,
+                Event::EndArray,
+                Event::EndObject,
+            ]
+        );
+        assert_eq!(events("42").unwrap(), vec![Event::Number("42".to_string())]);
+    }
+
+    #[test]
+    fn rejects_invalid_json_with_a_position() {
+        for input in &[
+            "",
+            "'asdf'",
+            "[1,]",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "{1: 2}",
+            "[01]",
+            "[1.]",
+            "[-]",
+            "[1e]",
+            "\"abc",
+            "\"\\x\"",
+            "\"\\ud83d\"",
+            "[tru]",
+            "[1]]",
+            "{} {}",
+            "[\"\u{1}\"]",
+        ] {
+            assert!(events(input).is_err(), "{:?} should be rejected", input);
+        }
+        match events("[1,\n  x]") {
+            Err(Error::Syntax { line, column, .. }) => assert_eq!((line, column), (2, 3)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn agrees_with_the_whole_document_parser() {
+        let input = r#"{"users": [{"name": "ann", "meaningOfLife": 42, "score": 1.5e2}, {"big": 18446744073709551615}], "ok": true}"#;
+        let mut reader = EventReader::new(input.as_bytes());
+        let streamed = reader.next_value().unwrap().unwrap();
+        assert_eq!(
+            streamed,
+            ::chapter_3::parse_input_to_json_value(input).unwrap()
+        );
+        assert_eq!(reader.next_value().unwrap(), None);
+    }
+
+    #[test]
+    fn yields_array_elements_one_at_a_time() {
+        let elements: Vec<Value> = array_elements(Cursor::new(r#"[{"a": 1}, [2], 3]"#))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(elements, vec![json!({"a": 1}), json!([2]), json!(3)]);
+        assert_eq!(array_elements("[]".as_bytes()).count(), 0);
+
+        let mut broken = array_elements("[1, 2, oops]".as_bytes());
+        assert_eq!(broken.next().unwrap().unwrap(), json!(1));
+        assert_eq!(broken.next().unwrap().unwrap(), json!(2));
+        assert!(broken.next().unwrap().is_err());
+        assert!(broken.next().is_none());
+        assert!(array_elements("{}".as_bytes()).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn names_types_like_the_whole_document_parser() {
+        for input in [
+            "42",
+            "-7",
+            "4.2",
+            "1e3",
+            "18446744073709551616",
+            "\"42\"",
+            "null",
+        ] {
+            let found = match array_elements(input.as_bytes()).next().unwrap() {
+                Err(Error::WrongType { found, .. }) => found,
+                other => panic!("unexpected {:?}", other),
+            };
+            let value = ::chapter_3::parse_input_to_json_value(input).unwrap();
+            assert_eq!(found, type_name(&value), "{}", input);
+        }
+    }
+
+    #[test]
+    fn finds_a_key_without_building_the_rest() {
+        let input =
+            r#"{"logs": [{"x": [1, 2, {"y": "z"}]}, "skip"], "meaningOfLife": 42, "after": [1"#;
+        // the document is cut off after the key, which proves nothing past it is read
+        assert_eq!(
+            get_meaning_of_life_from_reader(input.as_bytes()).unwrap(),
+            42
+        );
+        assert_eq!(
+            extract_from_reader::<_, String>(r#"{"a": [0, {"b": "c"}]}"#.as_bytes(), "/a/1/b")
+                .unwrap(),
+            "c"
+        );
+        match get_meaning_of_life_from_reader(r#"{"other": 1}"#.as_bytes()) {
+            Err(Error::MissingKey { path }) => assert_eq!(path, "/meaningOfLife"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match get_meaning_of_life_from_reader(r#"{"meaningOfLife": "42"}"#.as_bytes()) {
+            Err(Error::WrongType { found, .. }) => assert_eq!(found, "string"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(get_meaning_of_life_from_reader("{\"a\": oops}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn handles_large_inputs_incrementally() {
+        struct Generated {
+            remaining: usize,
+            pending: Vec<u8>,
+        }
+        impl Read for Generated {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.pending.is_empty() {
+                    self.pending = match self.remaining {
+                        0 => return Ok(0),
+                        1 => b"{\"n\": 1}]".to_vec(),
+                        100_000 => b"[{\"n\": 1},".to_vec(),
+                        _ => b"{\"n\": 1},".to_vec(),
+                    };
+                    self.remaining -= 1;
+                }
+                let n = buf.len().min(self.pending.len());
+                buf[..n].copy_from_slice(&self.pending[..n]);
+                self.pending.drain(..n);
+                Ok(n)
+            }
+        }
+        let total: i64 = array_elements(Generated {
+            remaining: 100_000,
+            pending: Vec::new(),
+        })
+        .map(|element| element.unwrap()["n"].as_i64().unwrap())
+        .sum();
+        assert_eq!(total, 100_000);
+    }
+}
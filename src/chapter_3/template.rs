@@ -0,0 +1,341 @@
+// JSON templates: string values with ${env.NAME} or ${ref:/pointer} placeholders, filled in at
+// load time. A string that is nothing but one placeholder takes the referenced value's type, so
+// "${ref:/defaults/meaningOfLife}" renders as 42, not "42"
+use std::collections::HashMap;
+use std::env;
+
+use serde_json::{Map, Value};
+
+use chapter_3::printer::minify;
+use chapter_3::{parse_input_to_json_value, Pointer, Result};
+use error::Error;
+
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    env: HashMap<String, String>,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    // the process environment, for ${env.USER} and friends
+    pub fn from_env() -> Context {
+        Context {
+            // env::vars panics on a name or value that isn't UTF-8; skip those instead
+            env: env::vars_os()
+                .filter_map(|(name, value)| {
+                    Some((name.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn var<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Context {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+}
+
+pub fn render(template: &Value, context: &Context) -> Result<Value> {
+    Renderer {
+        template,
+        context,
+        resolving: Vec::new(),
+        rendered: HashMap::new(),
+    }
+    .value(template, &Pointer::root())
+}
+
+pub fn render_input(input: &str, context: &Context) -> Result<Value> {
+    render(&parse_input_to_json_value(input)?, context)
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Literal(String),
+    Placeholder(&'a str),
+}
+
+struct Renderer<'a> {
+    template: &'a Value,
+    context: &'a Context,
+    // the refs being followed right now, to catch one that leads back to itself
+    resolving: Vec<String>,
+    // ref targets already rendered, by pointer, so a target referenced from many places is
+    // only rendered once
+    rendered: HashMap<String, Value>,
+}
+
+impl<'a> Renderer<'a> {
+    fn value(&mut self, value: &Value, path: &Pointer) -> Result<Value> {
+        Ok(match *value {
+            Value::String(ref s) => self.string(s, path)?,
+            Value::Array(ref items) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.value(item, &path.child(i.to_string())))
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Object(ref map) => {
+                let mut rendered = Map::new();
+                for (key, item) in map {
+                    rendered.insert(key.clone(), self.value(item, &path.child(key.as_str()))?);
+                }
+                Value::Object(rendered)
+            }
+            ref other => other.clone(),
+        })
+    }
+
+    fn string(&mut self, s: &str, path: &Pointer) -> Result<Value> {
+        let segments = segments(s).map_err(|reason| error(path, reason))?;
+        if let [Segment::Placeholder(expression)] = segments[..] {
+            return self.resolve(expression, path);
+        }
+        let mut out = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(&text),
+                Segment::Placeholder(expression) => match self.resolve(expression, path)? {
+                    Value::String(s) => out.push_str(&s),
+                    other => out.push_str(&minify(&other)),
+                },
+            }
+        }
+        Ok(Value::String(out))
+    }
+
+    fn resolve(&mut self, expression: &str, path: &Pointer) -> Result<Value> {
+        if let Some(name) = expression.strip_prefix("env.") {
+            return match self.context.env.get(name) {
+                Some(value) => Ok(Value::String(value.clone())),
+                None => Err(error(path, format!("env.{} is not set", name))),
+            };
+        }
+        let target = match expression.strip_prefix("ref:") {
+            Some(pointer) => Pointer::parse(pointer).map_err(|e| error(path, e.to_string()))?,
+            None => {
+                return Err(error(
+                    path,
+                    format!(
+                        "unknown placeholder ${{{}}}, expected env. or ref:",
+                        expression
+                    ),
+                ))
+            }
+        };
+        let key = target.to_string();
+        if let Some(value) = self.rendered.get(&key) {
+            return Ok(value.clone());
+        }
+        if self.resolving.contains(&key) {
+            let mut cycle = self.resolving.clone();
+            cycle.push(key);
+            return Err(error(
+                path,
+                format!("reference cycle {}", cycle.join(" -> ")),
+            ));
+        }
+        let raw = match target.resolve(self.template) {
+            Ok(raw) => raw,
+            Err(_) => return Err(error(path, format!("ref:{} points at nothing", key))),
+        };
+        self.resolving.push(key.clone());
+        let rendered = self.value(raw, &target);
+        self.resolving.pop();
+        let rendered = rendered?;
+        self.rendered.insert(key, rendered.clone());
+        Ok(rendered)
+    }
+}
+
+fn error<R: Into<String>>(path: &Pointer, reason: R) -> Error {
+    Error::Template {
+        path: path.to_string(),
+        reason: reason.into(),
+    }
+}
+
+// "$${" is a literal "${": the dollars in front of a "{" pair up, each "$$" writing one "$", and
+// an odd one left over starts a placeholder, so "$$${x}" is a "$" then x; any other "$" is itself
+fn segments(s: &str) -> ::std::result::Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let dollars = rest[..start].len() - rest[..start].trim_end_matches('$').len() + 1;
+        literal.push_str(&rest[..start + 1 - dollars]);
+        literal.push_str(&"$".repeat(dollars / 2));
+        if dollars.is_multiple_of(2) {
+            literal.push('{');
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err(format!("unterminated placeholder in {:?}", s)),
+        };
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal.split_off(0)));
+        }
+        segments.push(Segment::Placeholder(rest[start + 2..end].trim()));
+        rest = &rest[end + 1..];
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() || segments.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        Context::new()
+            .var("USER", "douglas")
+            .var("HOME", "/home/douglas")
+    }
+
+    fn failure(template: Value) -> (String, String) {
+        match render(&template, &context()) {
+            Err(Error::Template { path, reason }) => (path, reason),
+            other => panic!("expected a template error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn substitutes_with_types() {
+        let template = json!({
+            "defaults": {"meaningOfLife": 42, "tags": ["towel"]},
+            "answer": "${ref:/defaults/meaningOfLife}",
+            "tags": "${ref:/defaults/tags}",
+            "owner": "${env.USER}",
+            "greeting": "hi ${env.USER}, the answer is ${ref:/defaults/meaningOfLife}",
+            "nested": ["${ref:/answer}", {"home": "${env.HOME}/fixtures"}],
+            "plain": "no placeholders",
+            "count": 3
+        });
+        assert_eq!(
+            render(&template, &context()).unwrap(),
+            json!({
+                "defaults": {"meaningOfLife": 42, "tags": ["towel"]},
+                "answer": 42,
+                "tags": ["towel"],
+                "owner": "douglas",
+                "greeting": "hi douglas, the answer is 42",
+                "nested": [42, {"home": "/home/douglas/fixtures"}],
+                "plain": "no placeholders",
+                "count": 3
+            })
+        );
+    }
+
+    #[test]
+    fn escapes_and_edge_cases() {
+        assert_eq!(
+            render(
+                &json!([
+                    "$${env.USER}",
+                    "",
+                    "$",
+                    "a${ ref:/0 }b",
+                    "$$${env.USER}",
+                    "$$$${env.USER}",
+                    "$$5 {}"
+                ]),
+                &context()
+            )
+            .unwrap(),
+            json!([
+                "${env.USER}",
+                "",
+                "$",
+                "a${env.USER}b",
+                "$douglas",
+                "$${env.USER}",
+                "$$5 {}"
+            ])
+        );
+        assert_eq!(
+            segments("x${a}${b}").unwrap(),
+            vec![
+                Segment::Literal("x".to_string()),
+                Segment::Placeholder("a"),
+                Segment::Placeholder("b")
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let (path, reason) = failure(json!({"a": "${ref:/b}", "b": {"c": "${ref:/a}"}}));
+        assert_eq!(path, "/a");
+        assert_eq!(reason, "reference cycle /b -> /a -> /b");
+        let (path, reason) = failure(json!({"self": "${ref:/self}"}));
+        assert_eq!(
+            (path.as_str(), reason.as_str()),
+            ("/self", "reference cycle /self -> /self")
+        );
+        let (_, reason) = failure(json!({"x": {"y": "${ref:/x}"}}));
+        assert!(reason.starts_with("reference cycle /x -> /x"), "{}", reason);
+        // the same ref twice isn't a cycle
+        assert!(render(
+            &json!({"a": 1, "b": ["${ref:/a}", "${ref:/a}"]}),
+            &context()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn renders_each_ref_target_once() {
+        // every level refers to the one below twice; without the cache rendering the last would
+        // follow 2^40 refs
+        let mut template = Map::new();
+        template.insert("l0".to_string(), json!(""));
+        for i in 1..=40 {
+            let below = format!("${{ref:/l{}}}", i - 1);
+            template.insert(format!("l{}", i), json!(format!("{}{}", below, below)));
+        }
+        template.insert("top".to_string(), json!("[${ref:/l40}${env.USER}]"));
+        let rendered = render(&Value::Object(template), &context()).unwrap();
+        assert_eq!(rendered["l40"], json!(""));
+        assert_eq!(rendered["top"], json!("[douglas]"));
+    }
+
+    #[test]
+    fn errors_point_at_the_template_path() {
+        assert_eq!(
+            failure(json!({"users": [{"name": "${env.NOBODY}"}]})),
+            (
+                "/users/0/name".to_string(),
+                "env.NOBODY is not set".to_string()
+            )
+        );
+        assert_eq!(
+            failure(json!({"a": "${ref:/missing}"})),
+            (
+                "/a".to_string(),
+                "ref:/missing points at nothing".to_string()
+            )
+        );
+        assert_eq!(
+            failure(json!({"a": "${oops"})).1,
+            "unterminated placeholder in \"${oops\""
+        );
+        assert!(failure(json!({"a": "${USER}"}))
+            .1
+            .starts_with("unknown placeholder ${USER}"));
+        assert!(failure(json!({"a": "${ref:nope}"}))
+            .1
+            .contains("invalid JSON pointer"));
+        let message = render_input(r#"{"a": "${env.NOBODY}"}"#, &Context::new())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(message, "template error at /a: env.NOBODY is not set");
+    }
+}
@@ -1,3 +1,4 @@
+#[allow(unused_assignments)] // reassigning is the point of the example
 pub fn does_compile() -> i32 {
     let mut x = 42;
     x = 19;
@@ -0,0 +1,195 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use serde_json;
+
+use chapter_3::schema::Violation;
+
+// one error type for everything the crate can fail on, so callers can use `?` freely
+#[derive(Debug)]
+pub enum Error {
+    // the input wasn't valid JSON
+    Parse(serde_json::Error),
+    // the streaming reader hit invalid JSON at this 1-based line and column
+    Syntax {
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    // reading the input failed before we could parse it
+    Io(io::Error),
+    // the JSON was fine but the key we wanted isn't there
+    MissingKey {
+        path: String,
+    },
+    // the key is there but holds the wrong kind of value
+    WrongType {
+        path: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    // a JSON Pointer that doesn't follow RFC 6901
+    InvalidPointer {
+        pointer: String,
+        reason: &'static str,
+    },
+    // a JSONPath query that couldn't be parsed, with the byte offset it failed at
+    InvalidJsonPath {
+        query: String,
+        offset: usize,
+        reason: String,
+    },
+    // a JSON Schema we can't validate against, with the schema path that's wrong
+    InvalidSchema {
+        path: String,
+        reason: String,
+    },
+    // the JSON parsed but didn't match its schema; holds every violation found
+    Validation(Vec<Violation>),
+    // the input couldn't be read as the YAML, TOML or JSON5 it was taken for
+    Format {
+        format: &'static str,
+        reason: String,
+    },
+    // the crate was built without the cargo feature for this format
+    UnsupportedFormat {
+        format: &'static str,
+    },
+    // a JSON Patch was malformed or one of its operations couldn't be applied
+    Patch {
+        index: usize,
+        reason: String,
+    },
+    // valid JSON, but not the shape the typed document expects
+    Deserialize(serde_json::Error),
+    // keys a typed document doesn't know about, under UnknownFields::Deny
+    UnknownFields {
+        fields: Vec<String>,
+    },
+    // a document's "version" is newer (or older) than anything we can read
+    UnsupportedVersion {
+        version: u64,
+    },
+    // a number that can't be read as the type asked for, eg. 1e400 as an i128
+    Number {
+        number: String,
+        target: &'static str,
+        reason: &'static str,
+    },
+    // the input broke one of the ParseOptions limits or policies
+    LimitExceeded {
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    // a template placeholder that couldn't be filled in, at the template path it sits on
+    Template {
+        path: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse(ref e) => write!(f, "invalid JSON: {}", e),
+            Error::Syntax {
+                line,
+                column,
+                ref reason,
+            } => write!(
+                f,
+                "invalid JSON: {} at line {} column {}",
+                reason, line, column
+            ),
+            Error::Io(ref e) => write!(f, "could not read input: {}", e),
+            Error::MissingKey { ref path } => write!(f, "missing key at {}", path),
+            Error::WrongType {
+                ref path,
+                expected,
+                found,
+            } => write!(f, "expected {} at {}, found {}", expected, path, found),
+            Error::InvalidPointer {
+                ref pointer,
+                reason,
+            } => write!(f, "invalid JSON pointer {:?}: {}", pointer, reason),
+            Error::InvalidJsonPath {
+                ref query,
+                offset,
+                ref reason,
+            } => write!(
+                f,
+                "invalid JSONPath {:?} at offset {}: {}",
+                query, offset, reason
+            ),
+            Error::InvalidSchema {
+                ref path,
+                ref reason,
+            } => write!(f, "invalid schema at {:?}: {}", path, reason),
+            Error::Validation(ref violations) => {
+                write!(f, "{} schema violation(s)", violations.len())?;
+                for violation in violations {
+                    write!(f, "; {}", violation)?;
+                }
+                Ok(())
+            }
+            Error::Format { format, ref reason } => write!(f, "invalid {}: {}", format, reason),
+            Error::UnsupportedFormat { format } => write!(
+                f,
+                "{} support is not enabled, rebuild with `--features {}`",
+                format, format
+            ),
+            Error::Patch { index, ref reason } => {
+                write!(f, "patch operation {}: {}", index, reason)
+            }
+            Error::Deserialize(ref e) => write!(f, "unexpected document shape: {}", e),
+            Error::UnknownFields { ref fields } => {
+                write!(f, "unknown field(s): {}", fields.join(", "))
+            }
+            Error::UnsupportedVersion { version } => {
+                write!(f, "unsupported document version {}", version)
+            }
+            Error::Number {
+                ref number,
+                target,
+                reason,
+            } => write!(f, "{} can't be read as {}: it {}", number, target, reason),
+            Error::LimitExceeded {
+                line,
+                column,
+                ref reason,
+            } => write!(
+                f,
+                "input rejected: {} at line {} column {}",
+                reason, line, column
+            ),
+            Error::Template {
+                ref path,
+                ref reason,
+            } => write!(f, "template error at {}: {}", path, reason),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Parse(ref e) | Error::Deserialize(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Parse(e)
+    }
+}
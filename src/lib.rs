@@ -1,7 +1,29 @@
 #![allow(dead_code)]
 
+#[cfg_attr(test, macro_use)]
+extern crate serde_json;
+extern crate regex;
+extern crate serde;
+extern crate sha2;
+#[macro_use]
+extern crate serde_derive;
+
+#[cfg(feature = "cbor")]
+extern crate ciborium;
+#[cfg(feature = "json5")]
+extern crate json5;
+#[cfg(feature = "msgpack")]
+extern crate rmpv;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "toml")]
+extern crate toml;
+
+mod error;
+pub use error::Error;
+
 mod chapter_1;
-mod chapter_2;
-// mod chapter_3;
+pub mod chapter_2;
+pub mod chapter_3;
 mod chapter_4;
 mod chapter_6;
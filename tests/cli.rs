@@ -0,0 +1,162 @@
+// drives the rustoleum binary the way a script would: arguments, stdin, stdout and exit codes
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn rustoleum(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustoleum"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the binary runs");
+    // usage errors exit before reading stdin, so a broken pipe here is fine
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn fixture(name: &str, contents: &str) -> String {
+    let path: PathBuf = [env!("CARGO_TARGET_TMPDIR"), name].iter().collect();
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn gets_a_value_from_a_file_or_stdin() {
+    let file = fixture(
+        "get.json",
+        r#"{"meaningOfLife": 42, "author": {"name": "Douglas"}}"#,
+    );
+    let output = rustoleum(&["get", "/meaningOfLife", &file], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "42\n");
+
+    let output = rustoleum(
+        &["get", "/author", "-"],
+        r#"{"author": {"name": "Douglas"}}"#,
+    );
+    assert_eq!(stdout(&output), "{\n  \"name\": \"Douglas\"\n}\n");
+
+    let output = rustoleum(
+        &["get", "--raw", "/author/name"],
+        r#"{"author": {"name": "Douglas"}}"#,
+    );
+    assert_eq!(stdout(&output), "Douglas\n");
+}
+
+#[test]
+fn exit_codes_tell_parse_errors_from_missing_keys() {
+    let output = rustoleum(&["get", "/meaningOfLife"], "{\"meaningOfLife\": 'asdf'}");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("^^^^^^"), "{}", stderr(&output));
+    assert!(stderr(&output).contains("hint: single quotes"));
+
+    let output = rustoleum(&["get", "/meaningOfLife"], "{\"other\": 1}");
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(stderr(&output), "error: missing key at /meaningOfLife\n");
+    assert!(stdout(&output).is_empty());
+
+    let output = rustoleum(&["get", "/meaningOfLife", "/no/such/file.json"], "");
+    assert_eq!(output.status.code(), Some(6));
+
+    let output = rustoleum(&["get", "meaningOfLife"], "{}");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn validates_against_a_schema() {
+    let schema = fixture(
+        "schema.json",
+        r#"{"type": "object", "required": ["meaningOfLife"], "properties": {"meaningOfLife": {"type": "integer"}}}"#,
+    );
+    let output = rustoleum(
+        &["validate", "--schema", &schema],
+        r#"{"meaningOfLife": 42}"#,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "valid\n");
+
+    let output = rustoleum(
+        &["validate", "--schema", &schema],
+        r#"{"meaningOfLife": "42"}"#,
+    );
+    assert_eq!(output.status.code(), Some(5));
+    assert!(
+        stderr(&output).starts_with("invalid: /meaningOfLife: "),
+        "{}",
+        stderr(&output)
+    );
+
+    let output = rustoleum(&["validate"], "{}");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("usage:"));
+}
+
+#[test]
+fn formats_and_minifies() {
+    let messy = "{ \"tags\" :[\"towel\",\"fish\"], \"meaningOfLife\":42 }";
+    let output = rustoleum(&["fmt", "--indent", "4", "--compact-arrays", "3"], messy);
+    assert_eq!(
+        stdout(&output),
+        "{\n    \"meaningOfLife\": 42,\n    \"tags\": [\"towel\", \"fish\"]\n}\n"
+    );
+    let output = rustoleum(&["fmt", "--minify", "--ascii"], "{\"sign\": \"€\"}");
+    assert_eq!(stdout(&output), "{\"sign\":\"\\u20ac\"}\n");
+
+    let output = rustoleum(&["fmt", "--indent", "four"], "{}");
+    assert_eq!(output.status.code(), Some(2));
+    let output = rustoleum(&["fmt", "--tabs"], "{}");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn explains_itself() {
+    let output = rustoleum(&["help"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("usage:"));
+    assert_eq!(rustoleum(&[], "").status.code(), Some(2));
+    assert_eq!(rustoleum(&["frobnicate"], "").status.code(), Some(2));
+}
+
+#[test]
+fn generates_rust_types_from_samples() {
+    let first = fixture("codegen-1.json", r#"{"meaningOfLife": 42, "note": null}"#);
+    let second = fixture(
+        "codegen-2.json",
+        r#"{"meaningOfLife": 4.2, "note": "hi", "author": {"name": "Douglas"}}"#,
+    );
+    let output = rustoleum(&["codegen", "--name", "answer", &first, &second], "");
+    assert_eq!(output.status.code(), Some(0));
+    let source = stdout(&output);
+    assert!(source.contains("pub struct Answer {"), "{}", source);
+    assert!(source.contains("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub author: Option<Author>,"));
+    assert!(
+        source.contains("    #[serde(rename = \"meaningOfLife\")]\n    pub meaning_of_life: f64,")
+    );
+    assert!(source.contains("    pub note: Option<String>,"));
+    assert!(source.contains("pub struct Author {\n    pub name: String,\n}"));
+
+    let output = rustoleum(&["codegen"], r#"{"meaningOfLife": 42}"#);
+    assert!(stdout(&output).contains("pub struct Root {"));
+    let output = rustoleum(&["codegen"], r#"[{"meaningOfLife": 42}, {"note": "hi"}]"#);
+    assert_eq!(output.status.code(), Some(0));
+    let source = stdout(&output);
+    assert!(source.contains("    pub meaning_of_life: Option<i64>,"));
+    assert!(source.contains("    pub note: Option<String>,"));
+    let output = rustoleum(&["codegen"], "[1, 2]");
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(
+        stderr(&output),
+        "error: expected object at sample 1, found integer\n"
+    );
+}
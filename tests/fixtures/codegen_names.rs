@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Self2 {
+    #[serde(rename = "")]
+    pub field: i64,
+    #[serde(rename = "-")]
+    pub field2: i64,
+    #[serde(rename = "aB")]
+    pub a_b: i64,
+    #[serde(rename = "a_b")]
+    pub a_b2: i64,
+    #[serde(rename = "self")]
+    pub self_: Self3,
+    #[serde(rename = "self_")]
+    pub self_2: bool,
+    pub string: String2,
+    pub strings: Vec<String3>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Self3 {
+    pub k: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct String2 {
+    pub a: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct String3 {
+    pub b: Option<serde_json::Value>,
+}